@@ -0,0 +1,34 @@
+use std::{fs, path::Path};
+
+mod config {
+    pub(super) const PRESETS_DIR: &str = "interesting_configs";
+    pub(super) const PRESETS_FILE: &str = "presets.rs";
+}
+
+// lists the configs in `interesting_configs/` for `presets.rs` to embed, so new ones are picked up
+// without being added by hand
+fn main() {
+    println!("cargo:rerun-if-changed={}", config::PRESETS_DIR);
+
+    let mut names: Vec<String> = fs::read_dir(config::PRESETS_DIR)
+        .unwrap_or_else(|e| panic!("Failed to read {}: {}", config::PRESETS_DIR, e))
+        .map(|entry| entry.expect("Failed to read preset entry").path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .map(|path| {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            println!("cargo:rerun-if-changed={}", path.display());
+            format!("{:?}", name)
+        })
+        .collect();
+    names.sort();
+
+    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR should be set by cargo");
+    fs::write(
+        Path::new(&out_dir).join(config::PRESETS_FILE),
+        format!("presets![{}]", names.join(", ")),
+    )
+    .unwrap_or_else(|e| panic!("Failed to write {}: {}", config::PRESETS_FILE, e));
+}
@@ -1,18 +1,22 @@
+// each species deposits into its own channel of the RGBA canvas
+pub(crate) const MAX_SPECIES: usize = 4;
+// invocations per workgroup of the passes that run once per agent, matching their shaders
+pub(crate) const AGENT_WORKGROUP_SIZE: u32 = 64;
+
 #[repr(C)]
 #[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
 pub(crate) struct Agent {
     position: [f32; 2],
     heading: f32, // radians
-    _pad0: u32,
+    species: u32,
 }
 
 impl Agent {
-    pub fn new(mut rand_unit: impl FnMut() -> f32) -> Self {
-        let mut rand_signed_unit = || rand_unit() * 2. - 1.;
+    pub const fn new(position: [f32; 2], heading: f32, species: u32) -> Self {
         Self {
-            position: core::array::from_fn(|_| rand_signed_unit()),
-            heading: rand_signed_unit() * core::f32::consts::PI,
-            ..Default::default()
+            position,
+            heading,
+            species,
         }
     }
 }
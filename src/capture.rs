@@ -0,0 +1,98 @@
+use std::{num::NonZeroUsize, path::PathBuf};
+
+use crate::{context::Context, readback};
+
+pub(crate) struct CaptureOptions {
+    pub(crate) output_dir: PathBuf,
+    pub(crate) interval: NonZeroUsize,
+}
+
+// offscreen render target that is read back and written out as numbered PNG files
+pub(crate) struct FrameCapture {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    readback: wgpu::Buffer,
+
+    options: CaptureOptions,
+}
+
+impl FrameCapture {
+    pub fn new(ctx: &Context, dimensions: (u32, u32), options: CaptureOptions) -> Self {
+        std::fs::create_dir_all(&options.output_dir).expect("Failed to create output directory");
+
+        let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture Texture"),
+            size: wgpu::Extent3d {
+                width: dimensions.0,
+                height: dimensions.1,
+                ..Default::default()
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ctx.target_format(),
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Capture Texture View"),
+            ..Default::default()
+        });
+
+        let readback = readback::create_texture_buffer(ctx, &texture);
+
+        Self {
+            texture,
+            view,
+            readback,
+
+            options,
+        }
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn should_capture(&self, frame_number: usize) -> bool {
+        frame_number.is_multiple_of(self.options.interval.get())
+    }
+
+    pub fn copy_to_buffer(&self, encoder: &mut wgpu::CommandEncoder) {
+        readback::copy_texture_to_buffer(encoder, &self.texture, &self.readback);
+    }
+
+    // must be called after the commands recorded by `copy_to_buffer` have been submitted
+    pub fn save(&self, ctx: &Context, frame_number: usize) {
+        let mut pixels = readback::read_texture_buffer(ctx, &self.texture, &self.readback);
+
+        if matches!(
+            self.texture.format(),
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            pixels.chunks_exact_mut(4).for_each(|px| px.swap(0, 2));
+        }
+
+        let path = self
+            .options
+            .output_dir
+            .join(format!("{:06}.png", frame_number));
+
+        let file = std::fs::File::create(&path).expect("Failed to create frame file");
+        let mut encoder = png::Encoder::new(
+            std::io::BufWriter::new(file),
+            self.texture.width(),
+            self.texture.height(),
+        );
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&pixels))
+            .expect("Failed to write frame file");
+
+        log::info!("Saved frame to {}", path.display());
+    }
+}
@@ -0,0 +1,134 @@
+use std::{num::NonZeroUsize, path::PathBuf};
+
+use crate::{
+    capture::CaptureOptions,
+    config::{Config, ConfigError},
+    snapshot::{Snapshot, SnapshotError},
+    trace::{Trace, TraceError, TraceMode},
+    watcher::ConfigWatcher,
+};
+
+const DEFAULT_HEADLESS_FRAMES: usize = 1000;
+const DEFAULT_SNAPSHOT_PATH: &str = "snapshot.bin";
+
+#[derive(Clone, clap::Parser)]
+#[command(version, about = "Slime mould agent simulation")]
+pub struct Args {
+    /// Path to the JSON config file
+    #[arg(long, default_value = "config.json")]
+    config: PathBuf,
+
+    /// Override a config value by its dotted path, e.g. `agent.species.0.sensor_angle=30`
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_override)]
+    overrides: Vec<(String, String)>,
+
+    /// Override the config's random seed
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Number of frames to simulate before exiting [headless default: 1000]
+    #[arg(long)]
+    pub(crate) frames: Option<usize>,
+
+    /// Run without a window or surface
+    #[arg(long)]
+    pub(crate) headless: bool,
+
+    /// Directory to write rendered frames into as numbered PNG files
+    #[arg(long, requires = "headless")]
+    output_dir: Option<PathBuf>,
+
+    /// Write a frame to the output directory every K frames
+    #[arg(long, value_name = "K", default_value_t = NonZeroUsize::MIN, requires = "output_dir")]
+    capture_every: NonZeroUsize,
+
+    /// Resume from a snapshot file, using the config saved in it
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["config", "overrides", "seed"])]
+    load_snapshot: Option<PathBuf>,
+
+    /// Where to save snapshots: after the last frame when headless, or on `S` in a window
+    /// [window default: snapshot.bin]
+    #[arg(long, value_name = "PATH")]
+    save_snapshot: Option<PathBuf>,
+
+    /// Write a hash of the agents and canvas after every frame to a trace file
+    #[arg(long, value_name = "PATH", requires = "headless")]
+    record_trace: Option<PathBuf>,
+
+    /// Check every frame against a recorded trace, failing if any of them differ
+    /// [default frames: the length of the trace]
+    #[arg(
+        long,
+        value_name = "PATH",
+        requires = "headless",
+        conflicts_with = "record_trace"
+    )]
+    verify_trace: Option<PathBuf>,
+}
+
+pub(crate) struct HeadlessOptions {
+    pub(crate) num_frames: usize,
+    pub(crate) capture: Option<CaptureOptions>,
+    pub(crate) save_snapshot: Option<PathBuf>,
+    pub(crate) trace: Option<Trace>,
+}
+
+impl Args {
+    pub(crate) fn load_config(&self) -> Result<Config, ConfigError> {
+        let mut config = Config::load_from_file(&self.config, &self.overrides)?;
+
+        if let Some(seed) = self.seed {
+            config.random_seed = seed;
+        }
+
+        Ok(config)
+    }
+
+    // reloads with the same overrides whenever the config file changes
+    pub(crate) fn config_watcher(&self) -> ConfigWatcher {
+        let args = self.clone();
+        ConfigWatcher::new(self.config.clone(), move || args.load_config())
+    }
+
+    pub(crate) fn load_snapshot(&self) -> Option<Result<Snapshot, SnapshotError>> {
+        self.load_snapshot.as_deref().map(Snapshot::load)
+    }
+
+    pub(crate) fn snapshot_path(&self) -> PathBuf {
+        self.save_snapshot
+            .clone()
+            .unwrap_or_else(|| DEFAULT_SNAPSHOT_PATH.into())
+    }
+
+    pub(crate) fn headless_options(&self) -> Result<HeadlessOptions, TraceError> {
+        let trace = self
+            .record_trace
+            .clone()
+            .map(TraceMode::Record)
+            .or_else(|| self.verify_trace.clone().map(TraceMode::Verify))
+            .map(Trace::new)
+            .transpose()?;
+
+        let num_frames = self
+            .frames
+            .or_else(|| trace.as_ref().and_then(Trace::expected_frames))
+            .unwrap_or(DEFAULT_HEADLESS_FRAMES);
+
+        Ok(HeadlessOptions {
+            num_frames,
+            capture: self.output_dir.clone().map(|output_dir| CaptureOptions {
+                output_dir,
+                interval: self.capture_every,
+            }),
+            save_snapshot: self.save_snapshot.clone(),
+            trace,
+        })
+    }
+}
+
+fn parse_override(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(key, value)| (key.trim().to_owned(), value.trim().to_owned()))
+        .filter(|(key, _)| !key.is_empty())
+        .ok_or_else(|| format!("expected KEY=VALUE, got `{}`", arg))
+}
@@ -1,67 +1,965 @@
-#[derive(serde::Deserialize, serde::Serialize)]
+use crate::{
+    agent::{AGENT_WORKGROUP_SIZE, Agent, MAX_SPECIES},
+    image::GreyscaleImage,
+};
+
+pub(crate) const MAX_PALETTE_STOPS: usize = 8;
+pub(crate) const MAX_DIFFUSE_RADIUS: u32 = 32;
+// sensors sum (2 * radius + 1)^2 texels, three times per agent per step
+pub(crate) const MAX_SENSOR_RADIUS: u32 = 16;
+// keeps `deposit_scale` fine enough even with as many agents as fit on the device
+pub(crate) const MAX_DEPOSIT_AMOUNT: f32 = 16.;
+// well beyond anything that still forms trails, they only keep the parameters finite
+const SPEED_RANGE: (f32, f32) = (0., 10.);
+const TURNING_SPEED_RANGE: (f32, f32) = (0., 1000.);
+const SENSOR_DISTANCE_RANGE: (f32, f32) = (0., 2.);
+const SENSOR_ANGLE_RANGE: (f32, f32) = (0., 180.);
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
 pub(crate) struct Config {
     pub(crate) width: u32,
     pub(crate) height: u32,
+    // resizes the canvas along with the window, `width` and `height` only set its starting size
+    // when running headless
+    #[serde(default)]
+    pub(crate) match_window: bool,
     pub(crate) num_agents: u32,
     pub(crate) random_seed: u64,
+    #[serde(default)]
+    pub(crate) spawn: Spawn,
+    #[serde(default)]
+    pub(crate) canvas_format: CanvasFormat,
     pub(crate) world: WorldConfig,
     pub(crate) agent: AgentConfig,
+    #[serde(default)]
+    pub(crate) display: DisplayConfig,
+}
+
+// initial layout of the agents, positions and radii are in the simulation's space;
+// only used when the simulation is (re)built, so changing it doesn't reset a running simulation
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "mode", rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) enum Spawn {
+    // scattered over the whole canvas with random headings
+    #[default]
+    Uniform,
+    // all at one point with random headings
+    Point {
+        #[serde(default)]
+        position: [f32; 2],
+    },
+    // scattered over a disc with random headings
+    Disc {
+        #[serde(default)]
+        centre: [f32; 2],
+        radius: f32,
+    },
+    // on a circle, facing its centre
+    RingInward {
+        #[serde(default)]
+        centre: [f32; 2],
+        radius: f32,
+    },
+    // on a circle, facing away from its centre
+    RingOutward {
+        #[serde(default)]
+        centre: [f32; 2],
+        radius: f32,
+    },
+    // scattered with a density following the brightness of a greyscale PNG stretched over the
+    // canvas, random headings
+    Image {
+        path: std::path::PathBuf,
+    },
 }
 
-#[derive(serde::Deserialize, serde::Serialize)]
+// storage format of the trail map, higher precision keeps slow decay from quantizing away
+#[derive(Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CanvasFormat {
+    Rgba8Unorm,
+    #[default]
+    Rgba16Float,
+    Rgba32Float,
+    // single channel, so only usable with a single species
+    R32Float,
+}
+
+impl CanvasFormat {
+    pub(crate) const fn texture_format(self) -> wgpu::TextureFormat {
+        match self {
+            Self::Rgba8Unorm => wgpu::TextureFormat::Rgba8Unorm,
+            Self::Rgba16Float => wgpu::TextureFormat::Rgba16Float,
+            Self::Rgba32Float => wgpu::TextureFormat::Rgba32Float,
+            Self::R32Float => wgpu::TextureFormat::R32Float,
+        }
+    }
+
+    pub(crate) const fn wgsl_name(self) -> &'static str {
+        match self {
+            Self::Rgba8Unorm => "rgba8unorm",
+            Self::Rgba16Float => "rgba16float",
+            Self::Rgba32Float => "rgba32float",
+            Self::R32Float => "r32float",
+        }
+    }
+
+    // intermediate blur results can exceed 1, which 8-bit textures would clamp
+    pub(crate) const fn blur_format(self) -> Self {
+        match self {
+            Self::Rgba8Unorm => Self::Rgba16Float,
+            _ => self,
+        }
+    }
+
+    pub(crate) const fn num_channels(self) -> usize {
+        match self {
+            Self::R32Float => 1,
+            _ => 4,
+        }
+    }
+}
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
 pub(crate) struct WorldConfig {
     pub(crate) decay_rate: f32,
     pub(crate) diffuse_radius: u32,
+    #[serde(default)]
+    pub(crate) diffuse_kernel: DiffuseKernel,
+    #[serde(default)]
+    pub(crate) boundary: Boundary,
+    // PNG stretched over the canvas whose dark pixels (below half brightness) are walls that
+    // agents can't enter and trail doesn't diffuse into
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) mask: Option<std::path::PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) food: Vec<FoodSource>,
+    // at most one agent per texel, agents that would move into an occupied one turn to a random
+    // heading and don't deposit instead, as in Jeff Jones' model
+    #[serde(default)]
+    pub(crate) exclusive_occupancy: bool,
 }
 
-#[derive(serde::Deserialize, serde::Serialize)]
+// keeps the trail at least at `strength` wherever it lies, in the channel of `species` or of
+// every species if it's left out
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) enum FoodSource {
+    // a disc in the simulation's space
+    Point {
+        position: [f32; 2],
+        radius: f32,
+        strength: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        species: Option<u32>,
+    },
+    // a PNG stretched over the canvas, scaled by its brightness
+    Image {
+        path: std::path::PathBuf,
+        strength: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        species: Option<u32>,
+    },
+}
+
+impl FoodSource {
+    pub(crate) const fn strength(&self) -> f32 {
+        match self {
+            Self::Point { strength, .. } | Self::Image { strength, .. } => *strength,
+        }
+    }
+
+    pub(crate) const fn species(&self) -> Option<u32> {
+        match self {
+            Self::Point { species, .. } | Self::Image { species, .. } => *species,
+        }
+    }
+}
+
+// weights of the taps within `diffuse_radius`
+#[derive(Clone, Copy, Default, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) enum DiffuseKernel {
+    #[default]
+    Box,
+    Gaussian {
+        sigma: f32,
+    },
+}
+
+// what happens to agents, sensors and diffusion at the edges of the canvas
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Boundary {
+    #[default]
+    Reflect,
+    Wrap,
+    Respawn,
+    Absorb,
+}
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
 pub(crate) struct AgentConfig {
+    pub(crate) draw_scale: f32,
+    pub(crate) species: Vec<SpeciesConfig>,
+}
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SpeciesConfig {
     pub(crate) speed: f32,
     pub(crate) turning_speed: f32,
     pub(crate) sensor_distance: f32,
     pub(crate) sensor_angle: f32,
     pub(crate) sensor_radius: u32,
-    pub(crate) draw_scale: f32,
+    // trail added to the canvas by each agent every step, adding up where agents crowd
+    #[serde(default = "SpeciesConfig::default_deposit_amount")]
+    pub(crate) deposit_amount: f32,
+    #[serde(default = "SpeciesConfig::default_color")]
+    pub(crate) color: [f32; 3],
+    // evenly spaced gradient stops that trail intensities from 0 to 1 are mapped through,
+    // defaults to a gradient from black to `color`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) palette: Option<Vec<[f32; 3]>>,
+    // how strongly each canvas channel attracts (positive) or repels (negative) this species,
+    // defaults to attraction to its own channel and repulsion from every other
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) weights: Option<Vec<f32>>,
+}
+
+impl SpeciesConfig {
+    const fn default_deposit_amount() -> f32 {
+        1.
+    }
+
+    const fn default_color() -> [f32; 3] {
+        [1.; 3]
+    }
+
+    pub(crate) fn palette_stops(&self) -> Vec<[f32; 3]> {
+        self.palette
+            .clone()
+            .unwrap_or_else(|| vec![[0.; 3], self.color])
+    }
+
+    pub(crate) fn channel_weights(&self, species_index: usize) -> [f32; MAX_SPECIES] {
+        self.weights.as_ref().map_or_else(
+            || core::array::from_fn(|i| if i == species_index { 1. } else { -1. }),
+            |weights| core::array::from_fn(|i| weights.get(i).copied().unwrap_or(0.)),
+        )
+    }
+}
+
+// how trail intensities are turned into colours on screen, the trail map itself is untouched
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct DisplayConfig {
+    pub(crate) fit: Fit,
+    pub(crate) exposure: f32,
+    // above 1 brightens faint trails, below 1 darkens them
+    pub(crate) gamma: f32,
+    // tints the canvas with a gradient across the screen
+    pub(crate) position_tint: bool,
+}
+
+// how a canvas whose aspect ratio differs from the window's is shown
+#[derive(Clone, Copy, Default, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Fit {
+    // the whole canvas, with black bars along two edges of the window
+    #[default]
+    Letterbox,
+    // the whole window, cropping two edges of the canvas
+    Fill,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            fit: Fit::default(),
+            exposure: 1.,
+            gamma: 1.,
+            position_tint: true,
+        }
+    }
 }
 
 impl Config {
-    pub(crate) fn load() -> Self {
-        cfg_if::cfg_if! {
-            if #[cfg(target_arch = "wasm32")] {
-                Self::web_defaults()
-            } else {
-                Self::load_from_file()
+    // half the size of the canvas in the simulation's space, which spans [-1, 1] vertically and
+    // as far horizontally as keeps a unit the same number of texels along both axes
+    pub(crate) fn logical_extent(&self) -> [f32; 2] {
+        [self.width as f32 / self.height as f32, 1.]
+    }
+
+    // fixed-point scale of the deposit counters, a power of two so that it divides out exactly,
+    // as fine as it can be without a texel's counter overflowing when every agent deposits into
+    // it in the same step
+    pub(crate) fn deposit_scale(&self) -> f32 {
+        let max_deposit = self
+            .agent
+            .species
+            .iter()
+            .map(|species| species.deposit_amount)
+            .fold(1., f32::max);
+        let max_scale = f64::from(u32::MAX) / (f64::from(self.num_agents) * f64::from(max_deposit));
+        max_scale.log2().floor().clamp(0., 16.).exp2() as f32
+    }
+
+    pub(crate) fn from_json(source: &str) -> Result<Self, ConfigError> {
+        let mut config: Self = serde_json::from_str(source).map_err(ConfigError::Parse)?;
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    // `overrides` are (dotted key, value) pairs, e.g. ("agent.species.0.sensor_angle", "30")
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn load_from_file(
+        path: &std::path::Path,
+        overrides: &[(String, String)],
+    ) -> Result<Self, ConfigError> {
+        let mut root: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).map_err(ConfigError::Read)?)
+                .map_err(ConfigError::Parse)?;
+
+        for (key, value) in overrides {
+            apply_override(&mut root, key, value)?;
+        }
+
+        let mut config: Self = serde_json::from_value(root).map_err(ConfigError::Parse)?;
+
+        if let Some(dir) = path.parent() {
+            config.resolve_paths(dir);
+        }
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    // image paths are relative to the config file, so configs can be launched from anywhere
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn resolve_paths(&mut self, dir: &std::path::Path) {
+        let food_paths = self
+            .world
+            .food
+            .iter_mut()
+            .filter_map(|source| match source {
+                FoodSource::Image { path, .. } => Some(path),
+                FoodSource::Point { .. } => None,
+            });
+        let spawn_path = match &mut self.spawn {
+            Spawn::Image { path } => Some(path),
+            _ => None,
+        };
+
+        for path in self
+            .world
+            .mask
+            .iter_mut()
+            .chain(food_paths)
+            .chain(spawn_path)
+        {
+            *path = dir.join(&*path);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn save_to_file(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+
+    // rejects values that can't be simulated, listing every one, and clamps ones that can but
+    // are out of range
+    pub(crate) fn validate(&mut self) -> Result<(), ConfigError> {
+        let mut v = Validator::default();
+
+        // the device is created with wgpu's default limits, see `Context::request_device`
+        let limits = wgpu::Limits::default();
+        let max_size = limits.max_texture_dimension_2d;
+        v.check(
+            (1..=max_size).contains(&self.width),
+            "width",
+            format!("1 to {}", max_size),
+            self.width,
+        );
+        v.check(
+            (1..=max_size).contains(&self.height),
+            "height",
+            format!("1 to {}", max_size),
+            self.height,
+        );
+
+        // the agent passes dispatch every agent along one dimension
+        let max_agents = (limits.max_compute_workgroups_per_dimension * AGENT_WORKGROUP_SIZE)
+            .min(limits.max_storage_buffer_binding_size / size_of::<Agent>() as u32);
+        v.check(
+            (1..=max_agents).contains(&self.num_agents),
+            "num_agents",
+            format!("1 to {}", max_agents),
+            self.num_agents,
+        );
+
+        let num_species = self.agent.species.len();
+        let max_species = MAX_SPECIES.min(self.canvas_format.num_channels());
+        v.check(
+            (1..=max_species).contains(&num_species),
+            "agent.species",
+            format!("1 to {} entries for this canvas_format", max_species),
+            format!("{} entries", num_species),
+        );
+
+        // the deposits buffer holds a counter per texel and species in one storage binding, and
+        // snapshots read the whole canvas back through one buffer
+        let texels = u64::from(self.width) * u64::from(self.height);
+        let deposits_size = texels.saturating_mul(num_species.max(1) as u64 * 4);
+        v.check(
+            deposits_size <= u64::from(limits.max_storage_buffer_binding_size),
+            "width * height",
+            format!(
+                "at most {} texels with {} species",
+                u64::from(limits.max_storage_buffer_binding_size) / (num_species.max(1) as u64 * 4),
+                num_species
+            ),
+            texels,
+        );
+        let texel_size = self
+            .canvas_format
+            .texture_format()
+            .block_copy_size(None)
+            .unwrap_or(0);
+        let canvas_size = (u64::from(self.width) * u64::from(texel_size))
+            .next_multiple_of(u64::from(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT))
+            .saturating_mul(u64::from(self.height));
+        v.check(
+            canvas_size <= limits.max_buffer_size,
+            "width * height",
+            format!(
+                "at most {} bytes of canvas in this canvas_format",
+                limits.max_buffer_size
+            ),
+            format!("{} bytes", canvas_size),
+        );
+
+        v.clamp_float("agent.draw_scale", &mut self.agent.draw_scale, 0., f32::MAX);
+
+        for (i, species) in self.agent.species.iter_mut().enumerate() {
+            let path = |field: &str| format!("agent.species.{}.{}", i, field);
+
+            for (field, value, (min, max)) in [
+                ("speed", &mut species.speed, SPEED_RANGE),
+                (
+                    "turning_speed",
+                    &mut species.turning_speed,
+                    TURNING_SPEED_RANGE,
+                ),
+                (
+                    "sensor_distance",
+                    &mut species.sensor_distance,
+                    SENSOR_DISTANCE_RANGE,
+                ),
+                (
+                    "sensor_angle",
+                    &mut species.sensor_angle,
+                    SENSOR_ANGLE_RANGE,
+                ),
+            ] {
+                v.clamp_float(&path(field), value, min, max);
+            }
+            v.check(
+                (0. ..=MAX_DEPOSIT_AMOUNT).contains(&species.deposit_amount),
+                path("deposit_amount"),
+                format!("0 to {}", MAX_DEPOSIT_AMOUNT),
+                species.deposit_amount,
+            );
+            v.clamp(
+                &path("sensor_radius"),
+                &mut species.sensor_radius,
+                0,
+                MAX_SENSOR_RADIUS,
+            );
+            for channel in &mut species.color {
+                v.clamp_float(&path("color"), channel, 0., 1.);
+            }
+
+            if let Some(weights) = &species.weights {
+                v.check(
+                    weights.len() <= MAX_SPECIES,
+                    path("weights"),
+                    format!("at most {} entries", MAX_SPECIES),
+                    format!("{} entries", weights.len()),
+                );
+            }
+
+            if let Some(palette) = &mut species.palette {
+                v.check(
+                    (1..=MAX_PALETTE_STOPS).contains(&palette.len()),
+                    path("palette"),
+                    format!("1 to {} stops", MAX_PALETTE_STOPS),
+                    format!("{} stops", palette.len()),
+                );
+                for channel in palette.iter_mut().flatten() {
+                    v.clamp_float(&path("palette"), channel, 0., 1.);
+                }
+            }
+        }
+
+        let world = &mut self.world;
+        v.check(
+            world.decay_rate >= 0.,
+            "world.decay_rate",
+            "0 to 1",
+            world.decay_rate,
+        );
+        if world.decay_rate >= 0. {
+            v.clamp_float("world.decay_rate", &mut world.decay_rate, 0., 1.);
+        }
+        v.clamp(
+            "world.diffuse_radius",
+            &mut world.diffuse_radius,
+            0,
+            MAX_DIFFUSE_RADIUS,
+        );
+
+        if let DiffuseKernel::Gaussian { sigma } = world.diffuse_kernel {
+            v.check(sigma > 0., "world.diffuse_kernel.sigma", "above 0", sigma);
+        }
+
+        if let Some(path) = &world.mask {
+            v.check_image(path, "world.mask");
+        }
+
+        for (i, source) in world.food.iter().enumerate() {
+            let path = |field: &str| format!("world.food.{}.{}", i, field);
+
+            v.check(
+                source.strength() >= 0.,
+                path("strength"),
+                "at least 0",
+                source.strength(),
+            );
+
+            if let Some(species) = source.species() {
+                v.check(
+                    (species as usize) < num_species,
+                    path("species"),
+                    format!("0 to {}", num_species.saturating_sub(1)),
+                    species,
+                );
+            }
+
+            match source {
+                FoodSource::Point { radius, .. } => {
+                    v.check(*radius > 0., path("radius"), "above 0", radius);
+                }
+                FoodSource::Image { path: image, .. } => {
+                    v.check_image(image, &path("path"));
+                }
             }
         }
+
+        match &self.spawn {
+            Spawn::Disc { radius, .. }
+            | Spawn::RingInward { radius, .. }
+            | Spawn::RingOutward { radius, .. } => {
+                v.check(*radius > 0., "spawn.radius", "above 0", radius);
+            }
+            Spawn::Image { path } => {
+                if let Some(image) = v.check_image(path, "spawn.path") {
+                    v.check(
+                        image.values.iter().any(|&value| value > 0.),
+                        "spawn.path",
+                        "an image with at least one pixel that isn't black",
+                        path.display(),
+                    );
+                }
+            }
+            Spawn::Uniform | Spawn::Point { .. } => {}
+        }
+
+        v.clamp_float("display.exposure", &mut self.display.exposure, 0., f32::MAX);
+        v.check(
+            self.display.gamma > 0.,
+            "display.gamma",
+            "above 0",
+            self.display.gamma,
+        );
+
+        if v.invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(v.invalid))
+        }
     }
+}
 
-    #[allow(unused)]
-    fn web_defaults() -> Self {
-        Self {
-            width: 720,
-            height: 720,
-            num_agents: 50000,
-            random_seed: 24,
-            world: WorldConfig {
-                decay_rate: 0.002,
-                diffuse_radius: 1,
-            },
-            agent: AgentConfig {
-                speed: 0.2,
-                turning_speed: 20.0,
-                sensor_distance: 0.06,
-                sensor_angle: 25.0,
-                sensor_radius: 3,
-                draw_scale: 0.0,
-            },
-        }
-    }
-
-    #[allow(unused)]
-    fn load_from_file() -> Self {
-        serde_json::from_str(
-            &std::fs::read_to_string("config.json").expect("Failed to read config file"),
+// a value that can't be used, e.g. `agent.species.0.deposit_amount` expected "at least 0" but
+// found "-1"
+#[derive(Debug)]
+pub(crate) struct InvalidField {
+    pub(crate) path: String,
+    pub(crate) expected: String,
+    pub(crate) found: String,
+}
+
+impl std::fmt::Display for InvalidField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: expected {}, found {}",
+            self.path, self.expected, self.found
         )
-        .expect("Failed to parse config file")
+    }
+}
+
+#[derive(Default)]
+struct Validator {
+    invalid: Vec<InvalidField>,
+}
+
+impl Validator {
+    fn check(
+        &mut self,
+        valid: bool,
+        path: impl Into<String>,
+        expected: impl Into<String>,
+        found: impl std::fmt::Display,
+    ) {
+        if !valid {
+            self.invalid.push(InvalidField {
+                path: path.into(),
+                expected: expected.into(),
+                found: found.to_string(),
+            });
+        }
+    }
+
+    // callers rule out NaN, which compares as in range
+    fn clamp<T: PartialOrd + Copy + std::fmt::Display>(
+        &mut self,
+        path: &str,
+        value: &mut T,
+        min: T,
+        max: T,
+    ) {
+        if *value < min || *value > max {
+            let clamped = if *value < min { min } else { max };
+            log::warn!(
+                "{} is {}, outside the usable range, using {} instead",
+                path,
+                value,
+                clamped
+            );
+            *value = clamped;
+        }
+    }
+
+    // NaN and infinities aren't clamped, they're reported instead
+    fn clamp_float(&mut self, path: &str, value: &mut f32, min: f32, max: f32) {
+        if value.is_finite() {
+            self.clamp(path, value, min, max);
+        } else {
+            self.check(false, path, "a finite number", *value);
+        }
+    }
+
+    fn check_image(&mut self, path: &std::path::Path, json_path: &str) -> Option<GreyscaleImage> {
+        GreyscaleImage::load(path)
+            .map_err(|e| {
+                self.check(
+                    false,
+                    json_path,
+                    "a readable PNG",
+                    format!("{} ({})", path.display(), e),
+                );
+            })
+            .ok()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum ConfigError {
+    #[cfg(not(target_arch = "wasm32"))]
+    Read(std::io::Error),
+    Parse(serde_json::Error),
+    #[cfg(not(target_arch = "wasm32"))]
+    UnknownKey(String),
+    // every field that failed validation
+    Invalid(Vec<InvalidField>),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Read(e) => write!(f, "failed to read config file: {}", e),
+            Self::Parse(e) => write!(f, "failed to parse config file: {}", e),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::UnknownKey(key) => write!(f, "unknown config key: {}", key),
+            Self::Invalid(fields) => {
+                write!(f, "invalid config:")?;
+                fields
+                    .iter()
+                    .try_for_each(|field| write!(f, "\n  {}", field))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+// the final key segment may be new, but every parent along the path must already exist;
+// misspelt final segments are caught when the result is deserialized
+#[cfg(not(target_arch = "wasm32"))]
+fn apply_override(root: &mut serde_json::Value, key: &str, value: &str) -> Result<(), ConfigError> {
+    let unknown_key = || ConfigError::UnknownKey(key.to_owned());
+
+    let (parent_path, leaf) = key.rsplit_once('.').unwrap_or(("", key));
+
+    let parent = parent_path
+        .split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(root, |node, segment| match node {
+            serde_json::Value::Object(map) => map.get_mut(segment),
+            serde_json::Value::Array(list) => list.get_mut(segment.parse::<usize>().ok()?),
+            _ => None,
+        })
+        .ok_or_else(unknown_key)?;
+
+    // values that aren't valid JSON (e.g. bare words) are taken as strings
+    let value =
+        serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_owned()));
+
+    let slot = match parent {
+        serde_json::Value::Object(map) => map.entry(leaf).or_insert(serde_json::Value::Null),
+        serde_json::Value::Array(list) => leaf
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| list.get_mut(i))
+            .ok_or_else(unknown_key)?,
+        _ => return Err(unknown_key()),
+    };
+    *slot = value;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = include_str!("../interesting_configs/hives.json");
+
+    fn root() -> serde_json::Value {
+        serde_json::from_str(SOURCE).unwrap()
+    }
+
+    fn overridden(key: &str, value: &str) -> Result<serde_json::Value, ConfigError> {
+        let mut root = root();
+        apply_override(&mut root, key, value)?;
+        Ok(root)
+    }
+
+    #[test]
+    fn override_nested_and_indexed() {
+        let root = overridden("world.decay_rate", "0.5").unwrap();
+        assert_eq!(root["world"]["decay_rate"], 0.5);
+
+        let root = overridden("agent.species.0.sensor_angle", "45").unwrap();
+        assert_eq!(root["agent"]["species"][0]["sensor_angle"], 45);
+
+        let root = overridden("agent.species.0.color.2", "0.25").unwrap();
+        assert_eq!(root["agent"]["species"][0]["color"][2], 0.25);
+    }
+
+    #[test]
+    fn override_adds_leaf_to_existing_parent() {
+        let root = overridden("world.mask", "maze.png").unwrap();
+        assert_eq!(root["world"]["mask"], "maze.png");
+    }
+
+    #[test]
+    fn override_parses_json_and_falls_back_to_strings() {
+        let root = overridden("world.boundary", "wrap").unwrap();
+        assert_eq!(root["world"]["boundary"], "wrap");
+
+        let root = overridden("world.boundary", "\"wrap\"").unwrap();
+        assert_eq!(root["world"]["boundary"], "wrap");
+
+        let root = overridden(
+            "world.diffuse_kernel",
+            r#"{"kind": "gaussian", "sigma": 2}"#,
+        )
+        .unwrap();
+        assert_eq!(root["world"]["diffuse_kernel"]["sigma"], 2);
+    }
+
+    #[test]
+    fn override_rejects_missing_parents_and_indices() {
+        for key in [
+            "display.gamma",
+            "wrld.decay_rate",
+            "agent.species.1.speed",
+            "agent.species.x.speed",
+            "agent.species.0.color.3",
+            "width.value",
+        ] {
+            assert!(
+                matches!(overridden(key, "1"), Err(ConfigError::UnknownKey(_))),
+                "{}",
+                key
+            );
+        }
+    }
+
+    fn config() -> Config {
+        serde_json::from_str(SOURCE).unwrap()
+    }
+
+    fn invalid_paths(mut config: Config) -> Vec<String> {
+        match config.validate() {
+            Err(ConfigError::Invalid(fields)) => fields.into_iter().map(|f| f.path).collect(),
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(()) => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn validate_accepts_preset() {
+        assert!(config().validate().is_ok());
+    }
+
+    #[test]
+    fn validate_lists_every_invalid_field() {
+        let mut config = config();
+        config.width = 0;
+        config.num_agents = 0;
+        config.world.decay_rate = -1.;
+        config.agent.species[0].deposit_amount = -1.;
+        config.display.gamma = 0.;
+
+        assert_eq!(
+            invalid_paths(config),
+            [
+                "width",
+                "num_agents",
+                "agent.species.0.deposit_amount",
+                "world.decay_rate",
+                "display.gamma",
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_checks_canvas_area_against_buffer_limits() {
+        let mut config = config();
+        config.width = 8192;
+        config.height = 8192;
+
+        assert!(invalid_paths(config).contains(&"width * height".to_owned()));
+    }
+
+    #[test]
+    fn validate_handles_widths_that_overflow_u32() {
+        let mut config = config();
+        config.width = u32::MAX;
+
+        assert!(invalid_paths(config).contains(&"width".to_owned()));
+    }
+
+    #[test]
+    fn validate_clamps_usable_values() {
+        let mut config = config();
+        config.world.decay_rate = 2.;
+        config.world.diffuse_radius = 1000;
+        config.agent.species[0].sensor_radius = 50;
+        config.agent.species[0].color = [1.5, -0.5, 0.5];
+        config.agent.species[0].speed = 20.;
+        config.agent.species[0].sensor_angle = -10.;
+        config.display.exposure = -1.;
+
+        config.validate().unwrap();
+        assert_eq!(config.world.decay_rate, 1.);
+        assert_eq!(config.world.diffuse_radius, MAX_DIFFUSE_RADIUS);
+        assert_eq!(config.agent.species[0].sensor_radius, MAX_SENSOR_RADIUS);
+        assert_eq!(config.agent.species[0].color, [1., 0., 0.5]);
+        assert_eq!(config.agent.species[0].speed, SPEED_RANGE.1);
+        assert_eq!(config.agent.species[0].sensor_angle, SENSOR_ANGLE_RANGE.0);
+        assert_eq!(config.display.exposure, 0.);
+    }
+
+    #[test]
+    fn validate_reports_non_finite_values_once() {
+        let mut config = config();
+        config.world.decay_rate = f32::NAN;
+        config.agent.species[0].turning_speed = f32::INFINITY;
+        config.display.exposure = f32::NAN;
+
+        assert_eq!(
+            invalid_paths(config),
+            [
+                "agent.species.0.turning_speed",
+                "world.decay_rate",
+                "display.exposure"
+            ]
+        );
+    }
+
+    #[test]
+    fn image_paths_resolve_against_config_dir() {
+        let mut config: Config = serde_json::from_str(SOURCE).unwrap();
+        config.world.mask = Some("maze.png".into());
+        config.world.food = vec![FoodSource::Image {
+            path: "/abs/food.png".into(),
+            strength: 1.,
+            species: None,
+        }];
+        config.spawn = Spawn::Image {
+            path: "spawn/start.png".into(),
+        };
+
+        config.resolve_paths(std::path::Path::new("configs"));
+
+        assert_eq!(
+            config.world.mask.unwrap(),
+            std::path::Path::new("configs/maze.png")
+        );
+        assert!(
+            config.world.food[0]
+                == FoodSource::Image {
+                    path: "/abs/food.png".into(),
+                    strength: 1.,
+                    species: None,
+                }
+        );
+        assert!(matches!(
+            config.spawn,
+            Spawn::Image { path } if path == std::path::Path::new("configs/spawn/start.png")
+        ));
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        assert!(serde_json::from_value::<Config>(root()).is_ok());
+        for key in [
+            "world.decay_rat",
+            "agent.speeeed",
+            "agent.species.0.sped",
+            "colour",
+        ] {
+            let root = overridden(key, "1").unwrap();
+            assert!(serde_json::from_value::<Config>(root).is_err(), "{}", key);
+        }
+
+        let food = r#"[{"kind": "point", "position": [0, 0], "radius": 0.1, "strength": 1}]"#;
+        let mut root = overridden("world.food", food).unwrap();
+        assert!(serde_json::from_value::<Config>(root.clone()).is_ok());
+
+        apply_override(&mut root, "world.food.0.strenght", "2").unwrap();
+        assert!(serde_json::from_value::<Config>(root).is_err());
     }
 }
@@ -3,6 +3,11 @@ use winit::{
     window::Window,
 };
 
+mod config {
+    pub(super) const HEADLESS_TARGET_FORMAT: wgpu::TextureFormat =
+        wgpu::TextureFormat::Rgba8UnormSrgb;
+}
+
 pub(super) struct Surface<'a> {
     pub(super) inner: wgpu::Surface<'a>,
     pub(super) config: wgpu::SurfaceConfiguration,
@@ -12,7 +17,7 @@ pub(super) struct Surface<'a> {
 pub(super) struct Context<'a> {
     pub(super) device: wgpu::Device,
     pub(super) queue: wgpu::Queue,
-    pub(super) surface: Surface<'a>,
+    pub(super) surface: Option<Surface<'a>>,
 }
 
 impl<'a> Surface<'a> {
@@ -58,10 +63,7 @@ impl<'a> Surface<'a> {
 
 impl<'a> Context<'a> {
     pub(super) async fn new(window: &'a Window) -> Self {
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::PRIMARY,
-            ..Default::default()
-        });
+        let instance = Self::create_instance();
 
         let surface = instance
             .create_surface(window)
@@ -77,21 +79,70 @@ impl<'a> Context<'a> {
 
         let surface = Surface::new(surface, &adapter, window).await;
 
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor::default(), None)
+        let (device, queue) = Self::request_device(&adapter).await;
+
+        Self {
+            device,
+            queue,
+            surface: Some(surface),
+        }
+    }
+
+    // no window and no surface, for running the simulation off-screen
+    pub(super) async fn new_headless() -> Self {
+        let instance = Self::create_instance();
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                compatible_surface: None,
+                ..Default::default()
+            })
             .await
-            .expect("Failed to create device and queue");
+            .expect("Failed to find adapter");
+
+        log::info!("Running headless on adapter: {:?}", adapter.get_info());
+
+        let (device, queue) = Self::request_device(&adapter).await;
 
         Self {
             device,
             queue,
-            surface,
+            surface: None,
         }
     }
 
-    pub fn configure_surface(&self) {
+    fn create_instance() -> wgpu::Instance {
+        wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::PRIMARY,
+            ..Default::default()
+        })
+    }
+
+    async fn request_device(adapter: &wgpu::Adapter) -> (wgpu::Device, wgpu::Queue) {
+        adapter
+            .request_device(&wgpu::DeviceDescriptor::default(), None)
+            .await
+            .expect("Failed to create device and queue")
+    }
+
+    pub fn surface(&self) -> &Surface<'a> {
+        self.surface.as_ref().expect("Context has no surface")
+    }
+
+    pub fn surface_mut(&mut self) -> &mut Surface<'a> {
+        self.surface.as_mut().expect("Context has no surface")
+    }
+
+    pub fn target_format(&self) -> wgpu::TextureFormat {
         self.surface
-            .inner
-            .configure(&self.device, &self.surface.config);
+            .as_ref()
+            .map_or(config::HEADLESS_TARGET_FORMAT, |surface| {
+                surface.config.format
+            })
+    }
+
+    pub fn configure_surface(&self) {
+        let surface = self.surface();
+        surface.inner.configure(&self.device, &surface.config);
     }
 }
@@ -0,0 +1,87 @@
+use crate::{
+    config::{Config, FoodSource},
+    image::{GreyscaleImage, ImageError},
+};
+
+mod config {
+    pub(super) const NUM_CHANNELS: usize = 4;
+}
+
+// the largest strength of any source, which the rasterized field is normalized by
+pub(crate) fn food_scale(config: &Config) -> f32 {
+    config
+        .world
+        .food
+        .iter()
+        .map(FoodSource::strength)
+        .fold(0., f32::max)
+}
+
+// every source drawn into an RGBA8 field at canvas resolution, row 0 at the bottom, with each
+// channel holding the strongest source over that texel relative to `food_scale`
+pub(crate) fn rasterize(config: &Config, dimensions: (u32, u32)) -> Result<Vec<u8>, ImageError> {
+    let (width, height) = (dimensions.0 as usize, dimensions.1 as usize);
+    let mut field = vec![0.; width * height * config::NUM_CHANNELS];
+
+    let scale = food_scale(config);
+    if scale <= 0. {
+        return Ok(vec![0; field.len()]);
+    }
+
+    let num_species = config.agent.species.len();
+    let extent = config.logical_extent();
+
+    for source in &config.world.food {
+        let channels = source.species().map_or(0..num_species, |species| {
+            species as usize..species as usize + 1
+        });
+
+        let mut emit = |texel: usize, value: f32| {
+            for channel in channels.clone() {
+                let slot = &mut field[texel * config::NUM_CHANNELS + channel];
+                *slot = f32::max(*slot, value / scale);
+            }
+        };
+
+        match source {
+            FoodSource::Point {
+                position,
+                radius,
+                strength,
+                ..
+            } => {
+                // texel centres in the simulation's space that fall inside the disc, only
+                // searching its bounding box
+                let texel_range = |centre: f32, size: usize, extent: f32| {
+                    let to_texel =
+                        |logical: f32| ((logical / extent * 0.5 + 0.5) * size as f32).max(0.);
+                    to_texel(centre - radius).floor() as usize
+                        ..(to_texel(centre + radius).ceil() as usize).min(size)
+                };
+
+                for y in texel_range(position[1], height, extent[1]) {
+                    let logical_y = ((y as f32 + 0.5) / height as f32 * 2. - 1.) * extent[1];
+                    for x in texel_range(position[0], width, extent[0]) {
+                        let logical_x = ((x as f32 + 0.5) / width as f32 * 2. - 1.) * extent[0];
+                        let distance = (logical_x - position[0]).hypot(logical_y - position[1]);
+                        if distance < *radius {
+                            emit(y * width + x, *strength);
+                        }
+                    }
+                }
+            }
+            FoodSource::Image { path, strength, .. } => {
+                GreyscaleImage::load(path)?
+                    .resample(dimensions)
+                    .into_iter()
+                    .enumerate()
+                    .for_each(|(texel, value)| emit(texel, value * strength));
+            }
+        }
+    }
+
+    Ok(field
+        .into_iter()
+        .map(|value| (value.clamp(0., 1.) * 255.).round() as u8)
+        .collect())
+}
@@ -0,0 +1,87 @@
+use std::path::Path;
+
+mod config {
+    // pixels darker than this are walls when the image is used as a mask
+    pub(super) const WALL_THRESHOLD: f32 = 0.5;
+}
+
+// brightness of every pixel in [0, 1], rows from the top of the image down
+pub(crate) struct GreyscaleImage {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) values: Vec<f32>,
+}
+
+impl GreyscaleImage {
+    // any PNG colour type is accepted, colours are averaged and alpha is ignored
+    pub fn load(path: &Path) -> Result<Self, ImageError> {
+        let file = std::fs::File::open(path).map_err(ImageError::Io)?;
+
+        let mut decoder = png::Decoder::new(std::io::BufReader::new(file));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(ImageError::Decode)?;
+
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).map_err(ImageError::Decode)?;
+        pixels.truncate(info.buffer_size());
+
+        let num_colors = match info.color_type {
+            png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => 1,
+            _ => 3,
+        };
+
+        let values = pixels
+            .chunks_exact(info.color_type.samples())
+            .map(|px| {
+                px[..num_colors].iter().map(|&c| f32::from(c)).sum::<f32>()
+                    / (255. * num_colors as f32)
+            })
+            .collect();
+
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            values,
+        })
+    }
+
+    // nearest-neighbour resample to `dimensions`, flipped so that row 0 is the bottom of the
+    // image like it is for the canvas
+    pub fn resample(&self, dimensions: (u32, u32)) -> Vec<f32> {
+        (0..dimensions.1)
+            .rev()
+            .flat_map(|y| (0..dimensions.0).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let src_x = (u64::from(x) * u64::from(self.width) / u64::from(dimensions.0)) as u32;
+                let src_y =
+                    (u64::from(y) * u64::from(self.height) / u64::from(dimensions.1)) as u32;
+                self.values[(src_y * self.width + src_x) as usize]
+            })
+            .collect()
+    }
+
+    // whether each texel of `resample` is a wall
+    pub fn walls(&self, dimensions: (u32, u32)) -> Vec<bool> {
+        self.resample(dimensions)
+            .into_iter()
+            .map(|value| value < config::WALL_THRESHOLD)
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum ImageError {
+    Io(std::io::Error),
+    Decode(png::DecodingError),
+}
+
+impl std::fmt::Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read image: {}", e),
+            Self::Decode(e) => write!(f, "failed to decode image: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
@@ -0,0 +1,154 @@
+use winit::keyboard::KeyCode;
+
+mod config {
+    pub(super) const DEFAULT_BRUSH_RADIUS: f32 = 0.03;
+    pub(super) const BRUSH_RADIUS_RANGE: (f32, f32) = (0.005, 0.5);
+    pub(super) const BRUSH_RADIUS_STEP: f32 = 1.25;
+
+    pub(super) const ATTRACTOR_RADIUS: f32 = 0.15;
+}
+
+pub(crate) const MAX_ATTRACTORS: usize = 16;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CursorMode {
+    Paint,
+    Erase,
+    Attract,
+    Repel,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum BrushAction {
+    Paint { channel: u32 },
+    Erase,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Brush {
+    pub(crate) position: [f32; 2],
+    pub(crate) radius: f32,
+    pub(crate) action: BrushAction,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Attractor {
+    pub(crate) position: [f32; 2],
+    pub(crate) radius: f32,
+    // positive attracts, negative repels
+    pub(crate) strength: f32,
+}
+
+// cursor state, positions are in the simulation's logical space
+pub(crate) struct Interaction {
+    mode: CursorMode,
+    cursor: Option<[f32; 2]>,
+    pressed: bool,
+
+    brush_radius: f32,
+    paint_species: u32,
+
+    attractors: Vec<Attractor>,
+    attractors_changed: bool,
+}
+
+impl Interaction {
+    pub fn new() -> Self {
+        Self {
+            mode: CursorMode::Paint,
+            cursor: None,
+            pressed: false,
+
+            brush_radius: config::DEFAULT_BRUSH_RADIUS,
+            paint_species: 0,
+
+            attractors: Vec::new(),
+            attractors_changed: false,
+        }
+    }
+
+    // returns whether the key was an interaction control
+    pub fn handle_key(&mut self, key: KeyCode, num_species: u32) -> bool {
+        match key {
+            KeyCode::KeyB => self.mode = CursorMode::Paint,
+            KeyCode::KeyE => self.mode = CursorMode::Erase,
+            KeyCode::KeyA => self.mode = CursorMode::Attract,
+            KeyCode::KeyR => self.mode = CursorMode::Repel,
+            KeyCode::KeyC => {
+                self.attractors.clear();
+                self.attractors_changed = true;
+            }
+            KeyCode::Tab => self.paint_species = (self.paint_species + 1) % num_species.max(1),
+            KeyCode::BracketLeft => self.scale_brush(config::BRUSH_RADIUS_STEP.recip()),
+            KeyCode::BracketRight => self.scale_brush(config::BRUSH_RADIUS_STEP),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn set_cursor(&mut self, position: Option<[f32; 2]>) {
+        self.cursor = position;
+    }
+
+    pub fn set_pressed(&mut self, pressed: bool) {
+        self.pressed = pressed;
+
+        let strength = match self.mode {
+            CursorMode::Attract => 1.,
+            CursorMode::Repel => -1.,
+            CursorMode::Paint | CursorMode::Erase => return,
+        };
+
+        if let Some(position) = self.cursor.filter(|_| pressed) {
+            if self.attractors.len() == MAX_ATTRACTORS {
+                self.attractors.remove(0);
+            }
+            self.attractors.push(Attractor {
+                position,
+                radius: config::ATTRACTOR_RADIUS,
+                strength,
+            });
+            self.attractors_changed = true;
+        }
+    }
+
+    pub fn attractors(&self) -> &[Attractor] {
+        &self.attractors
+    }
+
+    pub fn status(&self) -> String {
+        match self.mode {
+            CursorMode::Paint => format!("Paint #{}", self.paint_species),
+            CursorMode::Erase => "Erase".to_owned(),
+            CursorMode::Attract => "Attract".to_owned(),
+            CursorMode::Repel => "Repel".to_owned(),
+        }
+    }
+
+    // the paint or erase stroke to apply this frame, if any
+    pub fn brush(&self) -> Option<Brush> {
+        let action = match self.mode {
+            CursorMode::Paint => BrushAction::Paint {
+                channel: self.paint_species,
+            },
+            CursorMode::Erase => BrushAction::Erase,
+            CursorMode::Attract | CursorMode::Repel => return None,
+        };
+
+        self.cursor.filter(|_| self.pressed).map(|position| Brush {
+            position,
+            radius: self.brush_radius,
+            action,
+        })
+    }
+
+    // returns the attractors only if they changed since the last call
+    pub fn take_changed_attractors(&mut self) -> Option<&[Attractor]> {
+        core::mem::take(&mut self.attractors_changed).then_some(&self.attractors)
+    }
+
+    fn scale_brush(&mut self, factor: f32) {
+        let (min, max) = config::BRUSH_RADIUS_RANGE;
+        self.brush_radius = (self.brush_radius * factor).clamp(min, max);
+    }
+}
@@ -1,79 +1,336 @@
 mod agent;
+#[cfg(not(target_arch = "wasm32"))]
+mod capture;
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
 mod config;
 mod context;
+mod food;
+mod image;
+mod interaction;
+mod panel;
 mod pass;
+mod playback;
+mod presets;
+#[cfg(not(target_arch = "wasm32"))]
+mod readback;
+mod renderer;
+mod simulation;
+#[cfg(not(target_arch = "wasm32"))]
+mod snapshot;
+mod spawn;
 mod state;
+#[cfg(not(target_arch = "wasm32"))]
+mod trace;
+mod ui;
+#[cfg(not(target_arch = "wasm32"))]
+mod watcher;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::wasm_bindgen;
 
 use winit::{
-    dpi::PhysicalSize,
-    event::{ElementState, Event, KeyEvent, WindowEvent},
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{ElementState, Event, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
     event_loop::EventLoop,
     keyboard::{KeyCode, PhysicalKey},
     window::{Window, WindowBuilder},
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+pub use cli::Args;
+
 use config::Config;
 use context::Context;
-use state::State;
+use interaction::Interaction;
+use panel::{Panel, PanelAction};
+use playback::Playback;
+use renderer::Renderer;
+use simulation::Simulation;
+#[cfg(not(target_arch = "wasm32"))]
+use snapshot::Snapshot;
+use ui::Ui;
 
 struct App<'a> {
-    #[allow(dead_code)]
-    state: State,
-    simulate_world_pass: pass::SimulateWorld,
-    simulate_agents_pass: pass::SimulateAgents,
-    draw_world_pass: pass::DrawWorld,
-
-    #[allow(unused)]
-    draw_agents_pass: pass::DrawAgents,
+    config: Config,
+    simulation: Simulation,
+    renderer: Renderer,
+    playback: Playback,
+    interaction: Interaction,
+    // in [-1, 1] over the window, y up
+    cursor: Option<[f32; 2]>,
+    panning: bool,
+    ui: Ui,
+    panel: Panel,
+    // the preset last switched to, if any
+    preset: Option<usize>,
+    #[cfg(not(target_arch = "wasm32"))]
+    snapshot_path: std::path::PathBuf,
 
     ctx: Context<'a>,
 }
 
 impl<'a> App<'a> {
-    async fn new(window: &'a Window) -> Self {
-        let config = Config::load();
-
+    async fn new(
+        window: &'a Window,
+        mut config: Config,
+        #[cfg(not(target_arch = "wasm32"))] snapshot_path: std::path::PathBuf,
+    ) -> Self {
         let ctx = Context::new(window).await;
 
-        let state = State::init(&ctx, &config);
+        let dimensions = ctx.surface().dimensions();
+        if config.match_window && dimensions.0 > 0 && dimensions.1 > 0 {
+            (config.width, config.height) = dimensions;
+        }
 
-        let simulate_world_pass = pass::SimulateWorld::new(&ctx, &config, &state);
-        let simulate_agents_pass = pass::SimulateAgents::new(&ctx, &config, &state);
-        let draw_world_pass = pass::DrawWorld::new(&ctx, &state);
-        let draw_agents_pass = pass::DrawAgents::new(&ctx, &config);
+        let simulation = Simulation::new(&ctx, &config);
 
-        Self {
-            state,
+        let renderer = Renderer::new(&ctx, &config, &simulation.state);
 
-            simulate_world_pass,
-            simulate_agents_pass,
-            draw_world_pass,
-            draw_agents_pass,
+        let app = Self {
+            config,
+            simulation,
+            renderer,
+            playback: Playback::new(),
+            interaction: Interaction::new(),
+            cursor: None,
+            panning: false,
+            ui: Ui::new(&ctx),
+            panel: Panel::new(),
+            preset: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            snapshot_path,
 
             ctx,
+        };
+        app.update_title();
+
+        app
+    }
+
+    fn update_title(&self) {
+        let preset = self
+            .preset
+            .map_or_else(String::new, |i| format!(" - {}", presets::PRESETS[i].name));
+        self.ctx.surface().window.set_title(&format!(
+            "Agent Sim{} - {} - {}",
+            preset,
+            self.playback.status(),
+            self.interaction.status()
+        ));
+    }
+
+    fn reconfigure(&mut self, config: &Config) {
+        // the configured size is ignored when matching the window, so it doesn't force a rebuild
+        let mut config = config.clone();
+        if config.match_window {
+            (config.width, config.height) = self.simulation.state.dimensions;
+        }
+
+        let needs_rebuild = self.simulation.needs_rebuild(&config);
+        self.config = config;
+        if needs_rebuild {
+            log::info!("Rebuilding simulation state for new dimensions");
+            self.rebuild();
+        } else {
+            self.simulation.update_config(&self.ctx, &self.config);
+            self.renderer.update_config(&self.ctx, &self.config);
+        }
+        self.match_window();
+    }
+
+    // restarts the simulation from the current config
+    fn rebuild(&mut self) {
+        self.simulation = Simulation::new(&self.ctx, &self.config);
+        self.simulation
+            .set_attractors(&self.ctx, self.interaction.attractors());
+        self.renderer
+            .rebuild(&self.ctx, &self.config, &self.simulation.state);
+    }
+
+    // only rebuilds the simulation if the preset needs differently sized buffers, otherwise the
+    // current agents and trail carry on under its parameters
+    fn switch_preset(&mut self, index: usize) {
+        let preset = &presets::PRESETS[index];
+        match preset.config() {
+            Ok(config) => {
+                log::info!("Switching to preset {}", preset.name);
+                self.preset = Some(index);
+                self.reconfigure(&config);
+                self.update_title();
+            }
+            Err(e) => log::error!("Failed to load preset {}: {}", preset.name, e),
+        }
+    }
+
+    fn handle_panel_action(&mut self, action: PanelAction) {
+        match action {
+            PanelAction::Reconfigure(config) => self.reconfigure(&config),
+            PanelAction::Reset => self.rebuild(),
+            PanelAction::Reseed => {
+                self.config.random_seed = fastrand::u64(..);
+                self.rebuild();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            PanelAction::Save(path) => match self.config.save_to_file(&path) {
+                Ok(()) => log::info!("Saved config to {}", path.display()),
+                Err(e) => log::error!("Failed to save config: {}", e),
+            },
+            PanelAction::SwitchPreset(index) => self.switch_preset(index),
+        }
+    }
+
+    // with `match_window`, resamples the simulation into a canvas the size of the surface
+    fn match_window(&mut self) {
+        let dimensions = self.ctx.surface().dimensions();
+        if !self.config.match_window || self.simulation.state.dimensions == dimensions {
+            return;
+        }
+
+        log::info!("Resizing canvas to {}x{}", dimensions.0, dimensions.1);
+        (self.config.width, self.config.height) = dimensions;
+        self.simulation.resize(&self.ctx, &self.config);
+        self.simulation
+            .set_attractors(&self.ctx, self.interaction.attractors());
+        self.renderer
+            .rebuild(&self.ctx, &self.config, &self.simulation.state);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn restore(&mut self, snapshot: &Snapshot) {
+        self.simulation = Simulation::restore(&self.ctx, snapshot);
+        self.simulation
+            .set_attractors(&self.ctx, self.interaction.attractors());
+        self.renderer
+            .rebuild(&self.ctx, &snapshot.config, &self.simulation.state);
+        self.config = snapshot.config.clone();
+        self.match_window();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_snapshot(&self) {
+        let snapshot = Snapshot::capture(&self.ctx, &self.config, &self.simulation);
+        match snapshot.save(&self.snapshot_path) {
+            Ok(()) => log::info!(
+                "Saved frame {} to {}",
+                snapshot.frame_number,
+                self.snapshot_path.display()
+            ),
+            Err(e) => log::error!("Failed to save snapshot: {}", e),
         }
     }
 
+    fn handle_key(&mut self, key: KeyCode) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if key == KeyCode::KeyS {
+            self.save_snapshot();
+            return;
+        }
+
+        if key == KeyCode::F1 {
+            self.panel.toggle();
+            return;
+        }
+
+        if let Some(preset) = presets::handle_key(key, self.preset) {
+            self.switch_preset(preset);
+            return;
+        }
+
+        if key == KeyCode::Home {
+            self.renderer.camera.reset(&self.ctx);
+            self.update_cursor();
+            return;
+        }
+
+        if self.playback.handle_key(key)
+            || self
+                .interaction
+                .handle_key(key, self.simulation.state.num_species)
+        {
+            self.update_title();
+        }
+    }
+
+    fn handle_cursor(&mut self, position: Option<PhysicalPosition<f64>>) {
+        // window pixels to [-1, 1] with y pointing up
+        let size = self.ctx.surface().window.inner_size();
+        let position = position.map(|p| {
+            [
+                (p.x / f64::from(size.width) * 2. - 1.) as f32,
+                (1. - p.y / f64::from(size.height) * 2.) as f32,
+            ]
+        });
+
+        if self.panning
+            && let (Some(from), Some(to)) = (self.cursor, position)
+        {
+            self.renderer.camera.pan(&self.ctx, from, to);
+        }
+        self.cursor = position;
+        self.update_cursor();
+    }
+
+    fn handle_scroll(&mut self, delta: MouseScrollDelta) {
+        self.renderer
+            .camera
+            .scroll(&self.ctx, self.cursor.unwrap_or_default(), delta);
+        self.update_cursor();
+    }
+
+    // the point under the cursor moves whenever the cursor or the camera does
+    fn update_cursor(&mut self) {
+        let camera = &self.renderer.camera;
+        self.interaction
+            .set_cursor(self.cursor.map(|p| camera.to_logical(p)));
+    }
+
     fn resize(&mut self, new_size: Option<PhysicalSize<u32>>) {
-        let new_size = new_size.unwrap_or(self.ctx.surface.dimensions().into());
+        let new_size = new_size.unwrap_or(self.ctx.surface().dimensions().into());
         if new_size.width > 0 && new_size.height > 0 {
-            self.ctx.surface.set_dimensions(new_size);
+            self.ctx.surface_mut().set_dimensions(new_size);
             self.ctx.configure_surface();
+            self.renderer
+                .resize(&self.ctx, self.ctx.surface().dimensions());
+            self.match_window();
         }
     }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        self.state.update();
-
-        let frame = self.ctx.surface.inner.get_current_texture()?;
+        let frame = self.ctx.surface().inner.get_current_texture()?;
 
         let frame_view = frame
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
+        let mut actions = Vec::new();
+        self.ui.run(&self.ctx, self.ctx.surface().window, |ctx| {
+            actions = self.panel.show(ctx, &self.config, self.preset);
+        });
+        for action in actions {
+            self.handle_panel_action(action);
+        }
+
+        self.simulation
+            .set_brush(&self.ctx, self.interaction.brush());
+        if let Some(attractors) = self.interaction.take_changed_attractors() {
+            self.simulation.set_attractors(&self.ctx, attractors);
+        }
+
+        // each step is submitted on its own so that per-step params uploads aren't overwritten
+        for _ in 0..self.playback.take_steps() {
+            let mut encoder =
+                self.ctx
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("Simulation Command Encoder"),
+                    });
+
+            self.simulation.step(&self.ctx, &mut encoder);
+
+            self.ctx.queue.submit(Some(encoder.finish()));
+        }
+
         let mut encoder = self
             .ctx
             .device
@@ -81,34 +338,10 @@ impl<'a> App<'a> {
                 label: Some("Command Encoder"),
             });
 
-        {
-            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-                label: Some("Compute Pass"),
-                ..Default::default()
-            });
-
-            self.simulate_world_pass.run(&mut compute_pass, &self.state);
-            self.simulate_agents_pass
-                .run(&self.ctx, &mut compute_pass, &self.state);
-        }
-
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &frame_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                ..Default::default()
-            });
-
-            self.draw_world_pass.run(&mut render_pass, &self.state);
-            self.draw_agents_pass.run(&mut render_pass, &self.state);
-        }
+        self.simulation.apply_brush(&mut encoder);
+        self.renderer
+            .render(&mut encoder, &frame_view, &self.simulation.state);
+        self.ui.render(&self.ctx, &mut encoder, &frame_view);
 
         self.ctx.queue.submit(Some(encoder.finish()));
         frame.present();
@@ -117,8 +350,7 @@ impl<'a> App<'a> {
     }
 }
 
-#[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
-pub async fn run() {
+fn init_logging() {
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
             std::panic::set_hook(Box::new(console_error_panic_hook::hook));
@@ -127,7 +359,133 @@ pub async fn run() {
             env_logger::init();
         }
     }
+}
 
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(start)]
+pub async fn run() {
+    init_logging();
+
+    let config = presets::PRESETS[presets::default_preset()]
+        .config()
+        .unwrap_or_else(|e| panic!("Failed to load default preset: {}", e));
+    run_windowed(config, None, || None).await;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run(args: Args) {
+    init_logging();
+
+    let snapshot = args
+        .load_snapshot()
+        .map(|snapshot| snapshot.unwrap_or_else(|e| panic!("Failed to load snapshot: {}", e)));
+
+    let config = match &snapshot {
+        Some(snapshot) => snapshot.config.clone(),
+        // invalid configs are reported field by field, which a panic message would bury
+        None => args.load_config().unwrap_or_else(|e| {
+            log::error!("Failed to load config: {}", e);
+            std::process::exit(1);
+        }),
+    };
+
+    if args.headless {
+        let options = args
+            .headless_options()
+            .unwrap_or_else(|e| panic!("Failed to load trace: {}", e));
+        run_headless(config, snapshot, options).await;
+    } else {
+        // a restored run keeps the config it was saved with
+        let mut watcher = snapshot.is_none().then(|| args.config_watcher());
+        run_windowed(
+            config,
+            args.frames,
+            move || watcher.as_mut().and_then(|watcher| watcher.poll()),
+            snapshot,
+            args.snapshot_path(),
+        )
+        .await;
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn run_headless(config: Config, snapshot: Option<Snapshot>, options: cli::HeadlessOptions) {
+    let cli::HeadlessOptions {
+        num_frames,
+        capture,
+        save_snapshot,
+        mut trace,
+    } = options;
+
+    let ctx = Context::new_headless().await;
+
+    let mut simulation = match &snapshot {
+        Some(snapshot) => Simulation::restore(&ctx, snapshot),
+        None => Simulation::new(&ctx, &config),
+    };
+
+    let capture = capture.map(|options| {
+        (
+            Renderer::new(&ctx, &config, &simulation.state),
+            capture::FrameCapture::new(&ctx, simulation.state.dimensions, options),
+        )
+    });
+
+    for _ in 0..num_frames {
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Command Encoder"),
+            });
+
+        simulation.step(&ctx, &mut encoder);
+
+        let frame_number = simulation.state.frame_number;
+        let capture = capture
+            .as_ref()
+            .filter(|(_, frame_capture)| frame_capture.should_capture(frame_number));
+
+        if let Some((renderer, frame_capture)) = capture {
+            renderer.render(&mut encoder, frame_capture.view(), &simulation.state);
+            frame_capture.copy_to_buffer(&mut encoder);
+        }
+
+        ctx.queue.submit(Some(encoder.finish()));
+        ctx.device.poll(wgpu::Maintain::Wait);
+
+        if let Some((_, frame_capture)) = capture {
+            frame_capture.save(&ctx, frame_number);
+        }
+
+        if let Some(trace) = trace.as_mut() {
+            trace.record(trace::FrameHash::capture(&ctx, &simulation));
+        }
+    }
+
+    log::info!("Simulated {} frames", simulation.state.frame_number);
+
+    if let Some(trace) = trace {
+        trace
+            .finish()
+            .unwrap_or_else(|e| panic!("Trace failed: {}", e));
+    }
+
+    if let Some(path) = save_snapshot {
+        Snapshot::capture(&ctx, &config, &simulation)
+            .save(&path)
+            .unwrap_or_else(|e| panic!("Failed to save snapshot: {}", e));
+        log::info!("Saved snapshot to {}", path.display());
+    }
+}
+
+// `reload_config` is polled every frame and returns a new config whenever one is available
+async fn run_windowed(
+    config: Config,
+    max_frames: Option<usize>,
+    mut reload_config: impl FnMut() -> Option<Config>,
+    #[cfg(not(target_arch = "wasm32"))] snapshot: Option<Snapshot>,
+    #[cfg(not(target_arch = "wasm32"))] snapshot_path: std::path::PathBuf,
+) {
     let event_loop = EventLoop::new().unwrap();
     let window = WindowBuilder::new()
         .with_title("Agent Sim")
@@ -162,23 +520,48 @@ pub async fn run() {
             .unwrap();
     }
 
-    let mut app = App::new(&window).await;
+    let mut app = App::new(
+        &window,
+        config,
+        #[cfg(not(target_arch = "wasm32"))]
+        snapshot_path,
+    )
+    .await;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(snapshot) = snapshot {
+        app.restore(&snapshot);
+    }
 
     let mut surface_configured = false;
 
     event_loop
         .run(move |event, control_flow| {
             if let Event::WindowEvent { ref event, .. } = event {
+                if app.ui.handle_event(app.ctx.surface().window, event) {
+                    return;
+                }
+
                 match event {
                     WindowEvent::RedrawRequested => {
-                        app.ctx.surface.window.request_redraw();
+                        app.ctx.surface().window.request_redraw();
 
                         if !surface_configured {
                             return;
                         }
 
+                        if let Some(config) = reload_config() {
+                            app.reconfigure(&config);
+                        }
+
                         match app.render() {
-                            Ok(_) => {}
+                            Ok(_) => {
+                                if max_frames
+                                    .is_some_and(|n| app.simulation.state.frame_number >= n)
+                                {
+                                    control_flow.exit();
+                                }
+                            }
                             Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
                                 app.resize(None)
                             }
@@ -201,6 +584,30 @@ pub async fn run() {
                             },
                         ..
                     } => control_flow.exit(),
+                    WindowEvent::KeyboardInput {
+                        event:
+                            KeyEvent {
+                                state: ElementState::Pressed,
+                                physical_key: PhysicalKey::Code(key),
+                                ..
+                            },
+                        ..
+                    } => app.handle_key(*key),
+                    WindowEvent::CursorMoved { position, .. } => {
+                        app.handle_cursor(Some(*position));
+                    }
+                    WindowEvent::CursorLeft { .. } => app.handle_cursor(None),
+                    WindowEvent::MouseInput {
+                        state,
+                        button: MouseButton::Left,
+                        ..
+                    } => app.interaction.set_pressed(state.is_pressed()),
+                    WindowEvent::MouseInput {
+                        state,
+                        button: MouseButton::Right | MouseButton::Middle,
+                        ..
+                    } => app.panning = state.is_pressed(),
+                    WindowEvent::MouseWheel { delta, .. } => app.handle_scroll(*delta),
                     WindowEvent::Resized(new_size) => {
                         app.resize(Some(*new_size));
                         surface_configured = true;
@@ -1,3 +1,11 @@
+#[cfg(not(target_arch = "wasm32"))]
+use clap::Parser;
+
+#[cfg(not(target_arch = "wasm32"))]
 fn main() {
-    pollster::block_on(agent_sim::run());
+    pollster::block_on(agent_sim::run(agent_sim::Args::parse()));
 }
+
+// the web build starts from `run` through wasm-bindgen instead
+#[cfg(target_arch = "wasm32")]
+fn main() {}
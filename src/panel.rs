@@ -0,0 +1,229 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+use crate::{
+    config::{Boundary, Config, DiffuseKernel, MAX_DIFFUSE_RADIUS},
+    presets::PRESETS,
+};
+
+mod config {
+    pub(super) const TITLE: &str = "Parameters";
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(super) const DEFAULT_SAVE_PATH: &str = "config.json";
+}
+
+// what the panel asks the app to do, see `App::handle_panel_action`
+pub(crate) enum PanelAction {
+    // edited parameters, applied without restarting where possible
+    Reconfigure(Config),
+    // restarts the simulation with the current config
+    Reset,
+    // restarts the simulation with a new random seed
+    Reseed,
+    #[cfg(not(target_arch = "wasm32"))]
+    Save(PathBuf),
+    // index into `PRESETS`
+    SwitchPreset(usize),
+}
+
+// sliders over the world and agent parameters, drawn with `Ui`
+pub(crate) struct Panel {
+    open: bool,
+    // why the last edit wasn't applied, until one is
+    error: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    save_path: String,
+}
+
+impl Panel {
+    pub fn new() -> Self {
+        Self {
+            open: true,
+            error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            save_path: config::DEFAULT_SAVE_PATH.to_owned(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        config: &Config,
+        preset: Option<usize>,
+    ) -> Vec<PanelAction> {
+        let mut actions = Vec::new();
+        let mut edited = config.clone();
+        let mut changed = false;
+        let mut open = self.open;
+
+        egui::Window::new(config::TITLE)
+            .open(&mut open)
+            .default_width(280.)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.button("Reset").clicked() {
+                            actions.push(PanelAction::Reset);
+                        }
+                        if ui.button("Reseed").clicked() {
+                            actions.push(PanelAction::Reseed);
+                        }
+                    });
+
+                    egui::ComboBox::from_label("preset")
+                        .selected_text(preset.map_or("none", |i| PRESETS[i].name))
+                        .show_ui(ui, |ui| {
+                            for (i, p) in PRESETS.iter().enumerate() {
+                                if ui.selectable_label(preset == Some(i), p.name).clicked() {
+                                    actions.push(PanelAction::SwitchPreset(i));
+                                }
+                            }
+                        });
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    self.save(ui, &mut actions);
+
+                    if let Some(error) = &self.error {
+                        ui.colored_label(ui.visuals().error_fg_color, error);
+                    }
+
+                    changed |= egui::CollapsingHeader::new("World")
+                        .default_open(true)
+                        .show(ui, |ui| world(ui, &mut edited))
+                        .body_returned
+                        .unwrap_or_default();
+                    changed |= egui::CollapsingHeader::new("Agents")
+                        .default_open(true)
+                        .show(ui, |ui| agents(ui, &mut edited))
+                        .body_returned
+                        .unwrap_or_default();
+                });
+            });
+        self.open = open;
+
+        // edits go through the same checks as loaded configs
+        if changed {
+            match edited.validate() {
+                Ok(()) => {
+                    self.error = None;
+                    actions.push(PanelAction::Reconfigure(edited));
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
+        actions
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save(&mut self, ui: &mut egui::Ui, actions: &mut Vec<PanelAction>) {
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.save_path);
+            if ui.button("Save as").clicked() {
+                actions.push(PanelAction::Save(PathBuf::from(&self.save_path)));
+            }
+        });
+    }
+}
+
+// sliders only keep edits within their range, so values from the config outside it aren't
+// rewritten just by being shown
+fn slider<'a, Num: egui::emath::Numeric>(
+    value: &'a mut Num,
+    range: std::ops::RangeInclusive<Num>,
+) -> egui::Slider<'a> {
+    egui::Slider::new(value, range).clamping(egui::SliderClamping::Edits)
+}
+
+// returns whether any parameter changed
+fn world(ui: &mut egui::Ui, config: &mut Config) -> bool {
+    let world = &mut config.world;
+
+    let mut changed = ui
+        .add(
+            slider(&mut world.decay_rate, 0.0..=0.1)
+                .logarithmic(true)
+                .text("decay rate"),
+        )
+        .changed();
+    changed |= ui
+        .add(slider(&mut world.diffuse_radius, 0..=MAX_DIFFUSE_RADIUS).text("diffuse radius"))
+        .changed();
+
+    let mut gaussian = matches!(world.diffuse_kernel, DiffuseKernel::Gaussian { .. });
+    if ui.checkbox(&mut gaussian, "gaussian kernel").changed() {
+        changed = true;
+        world.diffuse_kernel = if gaussian {
+            DiffuseKernel::Gaussian {
+                sigma: (world.diffuse_radius as f32 / 2.).max(0.5),
+            }
+        } else {
+            DiffuseKernel::Box
+        };
+    }
+    if let DiffuseKernel::Gaussian { sigma } = &mut world.diffuse_kernel {
+        changed |= ui
+            .add(slider(sigma, 0.1..=MAX_DIFFUSE_RADIUS as f32).text("sigma"))
+            .changed();
+    }
+
+    let boundary = world.boundary;
+    egui::ComboBox::from_label("boundary")
+        .selected_text(format!("{:?}", world.boundary))
+        .show_ui(ui, |ui| {
+            for boundary in [
+                Boundary::Reflect,
+                Boundary::Wrap,
+                Boundary::Respawn,
+                Boundary::Absorb,
+            ] {
+                ui.selectable_value(&mut world.boundary, boundary, format!("{:?}", boundary));
+            }
+        });
+
+    changed |= world.boundary != boundary;
+
+    changed
+        | ui.checkbox(&mut world.exclusive_occupancy, "exclusive occupancy")
+            .changed()
+}
+
+// returns whether any parameter changed
+fn agents(ui: &mut egui::Ui, config: &mut Config) -> bool {
+    let agent = &mut config.agent;
+
+    let mut changed = ui
+        .add(slider(&mut agent.draw_scale, 0.0..=4.).text("draw scale"))
+        .changed();
+
+    for (i, species) in agent.species.iter_mut().enumerate() {
+        changed |= egui::CollapsingHeader::new(format!("Species {}", i))
+            .default_open(i == 0)
+            .show(ui, |ui| {
+                [
+                    ui.add(slider(&mut species.speed, 0.0..=1.).text("speed")),
+                    ui.add(slider(&mut species.turning_speed, 0.0..=200.).text("turning speed")),
+                    ui.add(slider(&mut species.sensor_distance, 0.0..=0.2).text("sensor distance")),
+                    ui.add(slider(&mut species.sensor_angle, 0.0..=180.).text("sensor angle")),
+                    ui.add(slider(&mut species.sensor_radius, 0..=8).text("sensor radius")),
+                    ui.add(slider(&mut species.deposit_amount, 0.0..=5.).text("deposit amount")),
+                    ui.horizontal(|ui| {
+                        let response = ui.color_edit_button_rgb(&mut species.color);
+                        ui.label("color");
+                        response
+                    })
+                    .inner,
+                ]
+                .iter()
+                .any(egui::Response::changed)
+            })
+            .body_returned
+            .unwrap_or_default();
+    }
+
+    changed
+}
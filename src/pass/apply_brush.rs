@@ -0,0 +1,205 @@
+use crate::{
+    context::Context,
+    interaction::{Brush, BrushAction},
+    state,
+};
+
+use super::{params::Params, shader};
+
+mod config {
+    pub(super) const PASS_NAME: &str = "Apply Brush";
+    pub(super) const SHADER_SOURCE: &str = include_str!("apply_brush.wgsl");
+    pub(super) const SHADER_TILE_SIZE: u32 = 8;
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct BrushData {
+    position: [f32; 2],
+    radius: f32,
+    // 0: none, 1: paint, 2: erase
+    action: u32,
+    channel: u32,
+    _pad0: [u32; 3],
+}
+
+// paints into or erases the latest canvas under the cursor, once per rendered frame rather than
+// per simulation step so it works while paused and doesn't depend on the speed
+pub(crate) struct ApplyBrush {
+    pipeline: wgpu::ComputePipeline,
+    common_bind_group: wgpu::BindGroup,
+    canvas_bind_group: [wgpu::BindGroup; 2],
+
+    brush: Params<BrushData>,
+}
+
+impl ApplyBrush {
+    pub fn new(ctx: &Context, state: &state::State) -> Self {
+        let brush = Params::new(ctx, config::PASS_NAME, BrushData::default());
+
+        let texture = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        let common_bind_group_layout =
+            ctx.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some(&format!("{} Common Bind Group Layout", config::PASS_NAME)),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: brush.binding_type(),
+                            count: None,
+                        },
+                        texture(1),
+                    ],
+                });
+
+        let canvas_bind_group_layout =
+            ctx.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some(&format!("{} Canvas Bind Group Layout", config::PASS_NAME)),
+                    entries: &[
+                        texture(0),
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::StorageTexture {
+                                access: wgpu::StorageTextureAccess::WriteOnly,
+                                format: state.canvas_format.texture_format(),
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+
+        let pipeline_layout = ctx
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(&format!("{} Pipeline Layout", config::PASS_NAME)),
+                bind_group_layouts: &[&common_bind_group_layout, &canvas_bind_group_layout],
+                ..Default::default()
+            });
+
+        let common_bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{} Common Bind Group", config::PASS_NAME)),
+            layout: &common_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: brush.buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&state.mask_view),
+                },
+            ],
+        });
+
+        // the canvas the next step writes over holds the copy, so the one written here is the
+        // latest, as in `SimulateWorld`
+        let canvas_bind_group = core::array::from_fn(|i| {
+            ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&format!("{} Canvas Bind Group #{}", config::PASS_NAME, i)),
+                layout: &canvas_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&state.canvas_view[i]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(
+                            &state.canvas_view[(i + 1) % 2],
+                        ),
+                    },
+                ],
+            })
+        });
+
+        let shader = shader::create_canvas_shader_module(
+            ctx,
+            config::PASS_NAME,
+            config::SHADER_SOURCE,
+            state.canvas_format,
+        );
+
+        let pipeline = ctx
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(&format!("{} Pipeline", config::PASS_NAME)),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some("main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            });
+
+        Self {
+            pipeline,
+            common_bind_group,
+            canvas_bind_group,
+
+            brush,
+        }
+    }
+
+    pub fn set_brush(&mut self, ctx: &Context, brush: Option<Brush>) {
+        self.brush
+            .update(ctx, |b| *b = brush.map(BrushData::from).unwrap_or_default());
+    }
+
+    // does nothing while the brush isn't held down
+    pub fn run(&self, encoder: &mut wgpu::CommandEncoder, state: &state::State) {
+        if self.brush.data.action == 0 {
+            return;
+        }
+
+        let latest = state.latest_canvas();
+        let scratch = &state.canvas[state.frame_number % 2];
+        encoder.copy_texture_to_texture(
+            latest.as_image_copy(),
+            scratch.as_image_copy(),
+            latest.size(),
+        );
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some(&format!("{} Compute Pass", config::PASS_NAME)),
+            ..Default::default()
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.common_bind_group, &[]);
+        compute_pass.set_bind_group(1, &self.canvas_bind_group[state.frame_number % 2], &[]);
+        compute_pass.dispatch_workgroups(
+            state.dimensions.0.div_ceil(config::SHADER_TILE_SIZE),
+            state.dimensions.1.div_ceil(config::SHADER_TILE_SIZE),
+            1,
+        );
+    }
+}
+
+impl From<Brush> for BrushData {
+    fn from(brush: Brush) -> Self {
+        let (action, channel) = match brush.action {
+            BrushAction::Paint { channel } => (1, channel),
+            BrushAction::Erase => (2, 0),
+        };
+
+        Self {
+            position: brush.position,
+            radius: brush.radius,
+            action,
+            channel,
+            ..Default::default()
+        }
+    }
+}
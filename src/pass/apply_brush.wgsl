@@ -0,0 +1,45 @@
+const BRUSH_PAINT: u32 = 1;
+const BRUSH_ERASE: u32 = 2;
+
+struct Brush {
+    position: vec2<f32>,
+    radius: f32,
+    action: u32,
+    channel: u32,
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
+};
+
+@group(0) @binding(0) var<uniform> brush: Brush;
+@group(0) @binding(1) var mask: texture_2d<f32>;
+
+// a copy of the latest canvas, since the canvas can't be read while it's written to
+@group(1) @binding(0) var canvas_in: texture_2d<f32>;
+@group(1) @binding(1) var canvas_out: texture_storage_2d<CANVAS_FORMAT, write>;
+
+// texels outside the brush are left as they are, walls are never painted
+@compute @workgroup_size(8, 8)
+fn main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    let position = global_invocation_id.xy;
+    let dimensions = textureDimensions(canvas_out);
+    if (any(position >= dimensions)) { return; }
+    if (textureLoad(mask, position, 0).r > 0.5) { return; }
+
+    // maps the texel centre to the simulation's space, [-1, 1] vertically and wider or
+    // narrower horizontally to match the canvas aspect ratio
+    let extent = vec2<f32>(f32(dimensions.x) / f32(dimensions.y), 1.0);
+    let logical_position = ((vec2<f32>(position) + 0.5) / vec2<f32>(dimensions) * 2.0 - 1.0) * extent;
+    if (distance(logical_position, brush.position) >= brush.radius) { return; }
+
+    var value = textureLoad(canvas_in, position, 0);
+    if (brush.action == BRUSH_PAINT) {
+        value[brush.channel] = 1.0;
+    } else if (brush.action == BRUSH_ERASE) {
+        value = vec4<f32>(0);
+    }
+
+    textureStore(canvas_out, position, value);
+}
@@ -0,0 +1,167 @@
+use winit::event::MouseScrollDelta;
+
+use crate::{
+    config::{Config, Fit},
+    context::Context,
+};
+
+use super::params::Params;
+
+mod config {
+    pub(super) const PASS_NAME: &str = "Camera";
+    pub(super) const ZOOM_RANGE: (f32, f32) = (1., 256.);
+    // zoom factor per line scrolled
+    pub(super) const ZOOM_STEP: f32 = 1.2;
+    // touchpads scroll in pixels rather than lines
+    pub(super) const PIXELS_PER_LINE: f32 = 40.;
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct CameraData {
+    // half the canvas size in the simulation's space
+    extent: [f32; 2],
+    // size of the canvas on the target when zoomed out, where [1, 1] covers it exactly
+    scale: [f32; 2],
+    // point in the simulation's space at the middle of the target
+    centre: [f32; 2],
+    zoom: f32,
+    _pad0: u32,
+}
+
+// maps the simulation's space onto the render target, shared by the draw passes
+pub(crate) struct Camera {
+    params: Params<CameraData>,
+    fit: Fit,
+    target_dimensions: (u32, u32),
+    canvas_dimensions: (u32, u32),
+}
+
+impl Camera {
+    pub fn new(
+        ctx: &Context,
+        config: &Config,
+        target_dimensions: (u32, u32),
+        canvas_dimensions: (u32, u32),
+    ) -> Self {
+        let fit = config.display.fit;
+        let data = CameraData {
+            extent: config.logical_extent(),
+            scale: fit_scale(config.logical_extent(), fit, target_dimensions),
+            zoom: 1.,
+            ..Default::default()
+        };
+
+        Self {
+            params: Params::new(ctx, config::PASS_NAME, data),
+            fit,
+            target_dimensions,
+            canvas_dimensions,
+        }
+    }
+
+    pub fn update_config(&mut self, ctx: &Context, config: &Config) {
+        self.fit = config.display.fit;
+        self.update(ctx, |_| {});
+    }
+
+    // the canvas changed size or shape, the centre stays where it is if it's still on the canvas
+    pub fn set_canvas(&mut self, ctx: &Context, config: &Config, canvas_dimensions: (u32, u32)) {
+        self.fit = config.display.fit;
+        self.canvas_dimensions = canvas_dimensions;
+        let extent = config.logical_extent();
+        self.update(ctx, |c| c.extent = extent);
+    }
+
+    pub fn resize(&mut self, ctx: &Context, target_dimensions: (u32, u32)) {
+        self.target_dimensions = target_dimensions;
+        self.update(ctx, |_| {});
+    }
+
+    // zooms in when scrolling up, keeping the point under `position` (in target coordinates)
+    // where it is
+    pub fn scroll(&mut self, ctx: &Context, position: [f32; 2], delta: MouseScrollDelta) {
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y,
+            MouseScrollDelta::PixelDelta(pixels) => pixels.y as f32 / config::PIXELS_PER_LINE,
+        };
+        let factor = config::ZOOM_STEP.powf(lines);
+
+        let anchor = self.to_logical(position);
+        self.update(ctx, |c| {
+            c.zoom = (c.zoom * factor).clamp(config::ZOOM_RANGE.0, config::ZOOM_RANGE.1)
+        });
+        let moved = self.to_logical(position);
+        self.update(ctx, |c| {
+            c.centre = [
+                c.centre[0] + anchor[0] - moved[0],
+                c.centre[1] + anchor[1] - moved[1],
+            ]
+        });
+    }
+
+    // moves the canvas along with the cursor, both positions in target coordinates
+    pub fn pan(&mut self, ctx: &Context, from: [f32; 2], to: [f32; 2]) {
+        let from = self.to_logical(from);
+        let to = self.to_logical(to);
+        self.update(ctx, |c| {
+            c.centre = [c.centre[0] + from[0] - to[0], c.centre[1] + from[1] - to[1]]
+        });
+    }
+
+    pub fn reset(&mut self, ctx: &Context) {
+        self.update(ctx, |c| {
+            c.centre = [0., 0.];
+            c.zoom = 1.;
+        });
+    }
+
+    // target coordinates in [-1, 1], y up, to the simulation's space
+    pub fn to_logical(&self, position: [f32; 2]) -> [f32; 2] {
+        let CameraData {
+            extent,
+            scale,
+            centre,
+            zoom,
+            ..
+        } = self.params.data;
+        core::array::from_fn(|i| position[i] / (scale[i] * zoom) * extent[i] + centre[i])
+    }
+
+    // target pixels per canvas texel
+    pub fn magnification(&self) -> f32 {
+        let CameraData { scale, zoom, .. } = self.params.data;
+        self.target_dimensions.0 as f32 * scale[0] * zoom / self.canvas_dimensions.0 as f32
+    }
+
+    pub(super) fn binding_type(&self) -> wgpu::BindingType {
+        self.params.binding_type()
+    }
+
+    pub(super) fn binding(&self) -> wgpu::BindingResource<'_> {
+        self.params.buffer.as_entire_binding()
+    }
+
+    // applies `f`, then keeps the centre on the canvas and the fit up to date
+    fn update(&mut self, ctx: &Context, f: impl Fn(&mut CameraData)) {
+        let (fit, target_dimensions) = (self.fit, self.target_dimensions);
+        self.params.update(ctx, |c| {
+            f(c);
+            c.scale = fit_scale(c.extent, fit, target_dimensions);
+            c.centre = core::array::from_fn(|i| c.centre[i].clamp(-c.extent[i], c.extent[i]));
+        });
+    }
+}
+
+fn fit_scale(extent: [f32; 2], fit: Fit, (width, height): (u32, u32)) -> [f32; 2] {
+    let canvas_aspect = extent[0] / extent[1];
+    let target_aspect = width as f32 / height.max(1) as f32;
+
+    // letterboxing shrinks the canvas along the axis it overflows, filling grows it along
+    // the one it falls short of
+    let wider = target_aspect > canvas_aspect;
+    match (fit, wider) {
+        (Fit::Letterbox, true) | (Fit::Fill, false) => [canvas_aspect / target_aspect, 1.],
+        (Fit::Letterbox, false) | (Fit::Fill, true) => [1., target_aspect / canvas_aspect],
+    }
+}
@@ -2,7 +2,7 @@ use wgpu::util::DeviceExt;
 
 use crate::{agent::Agent, config::Config, context::Context, state};
 
-use super::params::Params;
+use super::{camera::Camera, params::Params};
 
 mod config {
     pub(super) const PASS_NAME: &str = "Draw Agents";
@@ -23,24 +23,31 @@ pub(crate) struct DrawAgents {
     agent_mesh: wgpu::Buffer,
     agent_num_vertices: u32,
 
-    #[allow(unused)]
     params: Params<ParamsData>,
 }
 
 impl DrawAgents {
-    pub fn new(ctx: &Context, config: &Config) -> Self {
+    pub fn new(ctx: &Context, config: &Config, camera: &Camera) -> Self {
         let params = Params::new(ctx, config::PASS_NAME, ParamsData::from(config));
 
         let common_bind_group_layout =
             ctx.device
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                     label: Some(&format!("{} Common Bind Group Layout", config::PASS_NAME)),
-                    entries: &[wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: params.binding_type(),
-                        count: None,
-                    }],
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::VERTEX,
+                            ty: params.binding_type(),
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::VERTEX,
+                            ty: camera.binding_type(),
+                            count: None,
+                        },
+                    ],
                 });
 
         let pipeline_layout = ctx
@@ -54,10 +61,16 @@ impl DrawAgents {
         let common_bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some(&format!("{} Common Bind Group", config::PASS_NAME)),
             layout: &common_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: params.buffer.as_entire_binding(),
-            }],
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: camera.binding(),
+                },
+            ],
         });
 
         let shader = ctx.device.create_shader_module(config::SHADER_SOURCE);
@@ -74,12 +87,12 @@ impl DrawAgents {
                         wgpu::VertexBufferLayout {
                             array_stride: core::mem::size_of::<Agent>() as wgpu::BufferAddress,
                             step_mode: wgpu::VertexStepMode::Instance,
-                            attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32],
+                            attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32, 2 => Uint32],
                         },
                         wgpu::VertexBufferLayout {
                             array_stride: core::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
                             step_mode: wgpu::VertexStepMode::Vertex,
-                            attributes: &wgpu::vertex_attr_array![2 => Float32x2],
+                            attributes: &wgpu::vertex_attr_array![3 => Float32x2],
                         },
                     ],
                     compilation_options: wgpu::PipelineCompilationOptions::default(),
@@ -87,7 +100,7 @@ impl DrawAgents {
                 fragment: Some(wgpu::FragmentState {
                     module: &shader,
                     entry_point: Some("fs_main"),
-                    targets: &[Some(ctx.surface.config.format.into())],
+                    targets: &[Some(ctx.target_format().into())],
                     compilation_options: wgpu::PipelineCompilationOptions::default(),
                 }),
                 primitive: wgpu::PrimitiveState {
@@ -125,6 +138,10 @@ impl DrawAgents {
         }
     }
 
+    pub fn update_config(&mut self, ctx: &Context, config: &Config) {
+        self.params.update(ctx, |p| *p = ParamsData::from(config));
+    }
+
     pub fn run(&self, render_pass: &mut wgpu::RenderPass, state: &state::State) {
         if self.params.data.scale == 0. {
             return;
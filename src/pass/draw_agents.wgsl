@@ -1,30 +1,50 @@
+// species of agents that have left the world under the absorb boundary
+const ABSORBED: u32 = 0xffffffffu;
+
 struct Params {
     scale: f32,
 };
 
+struct Camera {
+    extent: vec2<f32>,
+    scale: vec2<f32>,
+    centre: vec2<f32>,
+    zoom: f32,
+};
+
 struct VertexOutput {
     @builtin(position) screen_position: vec4<f32>,
     @location(0) uv: vec2<f32>,
 };
 
 @group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<uniform> camera: Camera;
 
 @vertex
 fn vs_main(
     @builtin(vertex_index) in_vertex_index: u32,
     @location(0) agent_pos: vec2<f32>,
     @location(1) agent_heading: f32,
-    @location(2) vertex_pos: vec2<f32>,
+    @location(2) agent_species: u32,
+    @location(3) vertex_pos: vec2<f32>,
 ) -> VertexOutput {
     var out: VertexOutput;
+    if (agent_species == ABSORBED) {
+        // degenerate, gets clipped
+        out.screen_position = vec4<f32>(0);
+        return out;
+    }
+
     let cs_heading = vec2<f32>(cos(agent_heading), sin(agent_heading));
     let vertex_position = vertex_pos * params.scale;
     let rotated_vertex_pos = vec2<f32>(
         vertex_position.x * cs_heading.x - vertex_position.y * cs_heading.y,
         vertex_position.x * cs_heading.y + vertex_position.y * cs_heading.x,
     );
-    out.uv = (agent_pos + rotated_vertex_pos) * 0.5 + 0.5;
-    out.screen_position = vec4<f32>(agent_pos + rotated_vertex_pos, 0, 1);
+    let canvas_position = (agent_pos + rotated_vertex_pos) / camera.extent;
+    out.uv = canvas_position * 0.5 + 0.5;
+    let camera_position = (agent_pos + rotated_vertex_pos - camera.centre) / camera.extent;
+    out.screen_position = vec4<f32>(camera_position * camera.scale * camera.zoom, 0, 1);
     return out;
 }
 
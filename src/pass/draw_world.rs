@@ -1,29 +1,81 @@
-use crate::{context::Context, state};
+use crate::{
+    agent::MAX_SPECIES,
+    config::{Config, MAX_PALETTE_STOPS, SpeciesConfig},
+    context::Context,
+    state,
+};
+
+use super::{camera::Camera, params::Params};
 
 mod config {
     pub(super) const PASS_NAME: &str = "Draw World";
     pub(super) const SHADER_SOURCE: wgpu::ShaderModuleDescriptor =
         wgpu::include_wgsl!("draw_world.wgsl");
+
+    // target pixels per canvas texel above which texels are drawn as sharp squares
+    pub(super) const NEAREST_MAGNIFICATION: f32 = 4.;
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct PaletteData {
+    stops: [[f32; 4]; MAX_PALETTE_STOPS],
+    num_stops: u32,
+    _pad0: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct ParamsData {
+    // one palette per canvas channel
+    palettes: [PaletteData; MAX_SPECIES],
+    num_species: u32,
+    exposure: f32,
+    gamma: f32,
+    position_tint: u32,
 }
 
 pub(crate) struct DrawWorld {
     pipeline: wgpu::RenderPipeline,
     common_bind_group: wgpu::BindGroup,
+    nearest_common_bind_group: wgpu::BindGroup,
     canvas_bind_group: [wgpu::BindGroup; 2],
+
+    params: Params<ParamsData>,
 }
 
 impl DrawWorld {
-    pub fn new(ctx: &Context, state: &state::State) -> Self {
+    pub fn new(ctx: &Context, config: &Config, state: &state::State, camera: &Camera) -> Self {
+        let params = Params::new(ctx, config::PASS_NAME, ParamsData::from(config));
+
         let common_bind_group_layout =
             ctx.device
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                     label: Some(&format!("{} Common Bind Group Layout", config::PASS_NAME)),
-                    entries: &[wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    }],
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(if state.canvas_filterable {
+                                wgpu::SamplerBindingType::Filtering
+                            } else {
+                                wgpu::SamplerBindingType::NonFiltering
+                            }),
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: params.binding_type(),
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: camera.binding_type(),
+                            count: None,
+                        },
+                    ],
                 });
 
         let canvas_bind_group_layout =
@@ -34,7 +86,9 @@ impl DrawWorld {
                         binding: 0,
                         visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            sample_type: wgpu::TextureSampleType::Float {
+                                filterable: state.canvas_filterable,
+                            },
                             view_dimension: wgpu::TextureViewDimension::D2,
                             multisampled: false,
                         },
@@ -50,15 +104,34 @@ impl DrawWorld {
                 ..Default::default()
             });
 
-        let common_bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some(&format!("{} Common Bind Group", config::PASS_NAME)),
-            layout: &common_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Sampler(&state.canvas_sampler),
-            }],
+        let nearest_sampler = ctx.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(&format!("{} Nearest Sampler", config::PASS_NAME)),
+            ..Default::default()
         });
 
+        // one per sampler, the nearest one is used when zoomed in close
+        let [common_bind_group, nearest_common_bind_group] =
+            [&state.canvas_sampler, &nearest_sampler].map(|sampler| {
+                ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some(&format!("{} Common Bind Group", config::PASS_NAME)),
+                    layout: &common_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::Sampler(sampler),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: params.buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: camera.binding(),
+                        },
+                    ],
+                })
+            });
+
         let canvas_bind_group = core::array::from_fn(|i| {
             ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
                 label: Some(&format!("{} Canvas Bind Group #{}", config::PASS_NAME, i)),
@@ -86,7 +159,7 @@ impl DrawWorld {
                 fragment: Some(wgpu::FragmentState {
                     module: &shader,
                     entry_point: Some("fs_main"),
-                    targets: &[Some(ctx.surface.config.format.into())],
+                    targets: &[Some(ctx.target_format().into())],
                     compilation_options: wgpu::PipelineCompilationOptions::default(),
                 }),
                 primitive: wgpu::PrimitiveState::default(),
@@ -99,14 +172,63 @@ impl DrawWorld {
         Self {
             pipeline,
             common_bind_group,
+            nearest_common_bind_group,
             canvas_bind_group,
+
+            params,
         }
     }
 
-    pub fn run(&self, render_pass: &mut wgpu::RenderPass, state: &state::State) {
+    pub fn update_config(&mut self, ctx: &Context, config: &Config) {
+        self.params.update(ctx, |p| *p = ParamsData::from(config));
+    }
+
+    pub fn run(&self, render_pass: &mut wgpu::RenderPass, state: &state::State, camera: &Camera) {
+        let common_bind_group = if camera.magnification() > config::NEAREST_MAGNIFICATION {
+            &self.nearest_common_bind_group
+        } else {
+            &self.common_bind_group
+        };
+
         render_pass.set_pipeline(&self.pipeline);
-        render_pass.set_bind_group(0, &self.common_bind_group, &[]);
+        render_pass.set_bind_group(0, common_bind_group, &[]);
         render_pass.set_bind_group(1, &self.canvas_bind_group[state.frame_number % 2], &[]);
         render_pass.draw(0..3, 0..1);
     }
 }
+
+impl From<&Config> for ParamsData {
+    fn from(config: &Config) -> Self {
+        let mut palettes = [PaletteData::default(); MAX_SPECIES];
+        palettes
+            .iter_mut()
+            .zip(&config.agent.species)
+            .for_each(|(palette, species)| *palette = species.into());
+
+        Self {
+            palettes,
+            num_species: config.agent.species.len() as _,
+            exposure: config.display.exposure,
+            gamma: config.display.gamma,
+            position_tint: config.display.position_tint.into(),
+        }
+    }
+}
+
+impl From<&SpeciesConfig> for PaletteData {
+    fn from(species: &SpeciesConfig) -> Self {
+        let palette = species.palette_stops();
+
+        let mut stops = [[0.; 4]; MAX_PALETTE_STOPS];
+        stops
+            .iter_mut()
+            .zip(&palette)
+            .for_each(|(stop, &[r, g, b])| *stop = [r, g, b, 1.]);
+
+        Self {
+            stops,
+            num_stops: palette.len().min(MAX_PALETTE_STOPS) as _,
+            ..Default::default()
+        }
+    }
+}
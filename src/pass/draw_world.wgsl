@@ -1,3 +1,6 @@
+const MAX_SPECIES: u32 = 4;
+const MAX_PALETTE_STOPS: u32 = 8;
+
 struct VertexOutput {
     @location(0) uv: vec2<f32>,
     @builtin(position) clip_position: vec4<f32>,
@@ -13,13 +16,66 @@ fn vs_main(
     return out;
 }
 
+struct Palette {
+    stops: array<vec4<f32>, MAX_PALETTE_STOPS>,
+    num_stops: u32,
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
+};
+
+struct Camera {
+    extent: vec2<f32>,
+    scale: vec2<f32>,
+    centre: vec2<f32>,
+    zoom: f32,
+};
+
+struct Params {
+    palettes: array<Palette, MAX_SPECIES>,
+    num_species: u32,
+    exposure: f32,
+    gamma: f32,
+    position_tint: u32,
+};
+
 @group(0) @binding(0) var canvas_sampler: sampler;
+@group(0) @binding(1) var<uniform> params: Params;
+@group(0) @binding(2) var<uniform> camera: Camera;
 
 @group(1) @binding(0) var canvas_texture: texture_2d<f32>;
 
+// `t` in [0, 1] is spread evenly across the palette's stops
+fn sample_palette(palette: Palette, t: f32) -> vec3<f32> {
+    let last = palette.num_stops - 1;
+    let position = t * f32(last);
+    let lower = min(u32(position), last);
+    let upper = min(lower + 1, last);
+    return mix(palette.stops[lower].rgb, palette.stops[upper].rgb, fract(position));
+}
+
 @fragment
 fn fs_main(
     in: VertexOutput,
 ) -> @location(0) vec4<f32> {
-    return textureSample(canvas_texture, canvas_sampler, in.uv) * vec4<f32>(in.uv, 1.0, 1.0);
+    // `in.uv` covers the target, which shows the canvas around the camera's centre;
+    // sampled before the bounds check to keep control flow uniform
+    let canvas_position = (in.uv * 2.0 - 1.0) / (camera.scale * camera.zoom) + camera.centre / camera.extent;
+    let uv = canvas_position * 0.5 + 0.5;
+    let trail = textureSample(canvas_texture, canvas_sampler, uv);
+    if (any(uv < vec2<f32>(0)) || any(uv > vec2<f32>(1))) {
+        return vec4<f32>(0, 0, 0, 1);
+    }
+
+    var color = vec3<f32>(0);
+    for (var i: u32 = 0; i < params.num_species; i += 1u) {
+        let intensity = pow(clamp(trail[i] * params.exposure, 0.0, 1.0), 1.0 / params.gamma);
+        color += sample_palette(params.palettes[i], intensity);
+    }
+
+    if (params.position_tint != 0) {
+        color *= vec3<f32>(uv, 1.0);
+    }
+
+    return vec4<f32>(min(color, vec3<f32>(1)), 1.0);
 }
@@ -1,10 +1,17 @@
+mod apply_brush;
+mod camera;
 mod draw_agents;
 mod draw_world;
 mod params;
+mod resample;
+mod shader;
 mod simulate_agents;
 mod simulate_world;
 
+pub(crate) use apply_brush::ApplyBrush;
+pub(crate) use camera::Camera;
 pub(crate) use draw_agents::DrawAgents;
 pub(crate) use draw_world::DrawWorld;
+pub(crate) use resample::Resample;
 pub(crate) use simulate_agents::SimulateAgents;
 pub(crate) use simulate_world::SimulateWorld;
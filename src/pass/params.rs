@@ -21,7 +21,6 @@ impl<D: NoUninit> Params<D> {
         Self { data, buffer }
     }
 
-    #[allow(unused)]
     pub(crate) fn update(&mut self, ctx: &Context, mut f: impl FnMut(&mut D)) {
         f(&mut self.data);
         ctx.queue
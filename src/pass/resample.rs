@@ -0,0 +1,137 @@
+use crate::{context::Context, state};
+
+use super::shader;
+
+mod config {
+    pub(super) const PASS_NAME: &str = "Resample";
+    pub(super) const SHADER_SOURCE: &str = include_str!("resample.wgsl");
+    pub(super) const SHADER_WORKGROUP_SIZE: u32 = crate::agent::AGENT_WORKGROUP_SIZE;
+    pub(super) const SHADER_TILE_SIZE: u32 = 8;
+}
+
+// carries the trail and agents of one state over into another of a different size; only run
+// once per resize, so nothing is kept around between runs
+pub(crate) struct Resample {
+    canvas_pipeline: wgpu::ComputePipeline,
+    agents_pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Resample {
+    pub fn new(ctx: &Context, source: &state::State, target: &state::State) -> Self {
+        let storage_buffer = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bind_group_layout =
+            ctx.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some(&format!("{} Bind Group Layout", config::PASS_NAME)),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::StorageTexture {
+                                access: wgpu::StorageTextureAccess::WriteOnly,
+                                format: target.canvas_format.texture_format(),
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                            },
+                            count: None,
+                        },
+                        storage_buffer(2, true),
+                        storage_buffer(3, false),
+                    ],
+                });
+
+        let pipeline_layout = ctx
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(&format!("{} Pipeline Layout", config::PASS_NAME)),
+                bind_group_layouts: &[&bind_group_layout],
+                ..Default::default()
+            });
+
+        let bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{} Bind Group", config::PASS_NAME)),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source.latest_canvas_view()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(target.latest_canvas_view()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: source.agents.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: target.agents.as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader = shader::create_canvas_shader_module(
+            ctx,
+            config::PASS_NAME,
+            config::SHADER_SOURCE,
+            target.canvas_format,
+        );
+
+        let create_pipeline = |entry_point| {
+            ctx.device
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some(&format!("{} Pipeline ({})", config::PASS_NAME, entry_point)),
+                    layout: Some(&pipeline_layout),
+                    module: &shader,
+                    entry_point: Some(entry_point),
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    cache: None,
+                })
+        };
+
+        Self {
+            canvas_pipeline: create_pipeline("resample_canvas"),
+            agents_pipeline: create_pipeline("rescale_agents"),
+            bind_group,
+        }
+    }
+
+    pub fn run(&self, compute_pass: &mut wgpu::ComputePass, target: &state::State) {
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+
+        compute_pass.set_pipeline(&self.canvas_pipeline);
+        compute_pass.dispatch_workgroups(
+            target.dimensions.0.div_ceil(config::SHADER_TILE_SIZE),
+            target.dimensions.1.div_ceil(config::SHADER_TILE_SIZE),
+            1,
+        );
+
+        compute_pass.set_pipeline(&self.agents_pipeline);
+        compute_pass.dispatch_workgroups(
+            target.num_agents.div_ceil(config::SHADER_WORKGROUP_SIZE),
+            1,
+            1,
+        );
+    }
+}
@@ -0,0 +1,53 @@
+struct Agent {
+    position: vec2<f32>,
+    heading: f32,
+    species: u32,
+};
+
+@group(0) @binding(0) var canvas_in: texture_2d<f32>;
+@group(0) @binding(1) var canvas_out: texture_storage_2d<CANVAS_FORMAT, write>;
+@group(0) @binding(2) var<storage, read> agents_in: array<Agent>;
+@group(0) @binding(3) var<storage, read_write> agents_out: array<Agent>;
+
+// half the canvas size in the simulation's space, see `simulate_agents.wgsl`
+fn logical_extent(dimensions: vec2<u32>) -> vec2<f32> {
+    return vec2<f32>(f32(dimensions.x) / f32(dimensions.y), 1.0);
+}
+
+fn load_clamped(pos: vec2<i32>) -> vec4<f32> {
+    let dimensions = vec2<i32>(textureDimensions(canvas_in));
+    return textureLoad(canvas_in, clamp(pos, vec2<i32>(0), dimensions - 1), 0);
+}
+
+// bilinear, since 32-bit float canvases can't be sampled with filtering
+@compute @workgroup_size(8, 8)
+fn resample_canvas(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    let position = global_invocation_id.xy;
+    let dimensions = textureDimensions(canvas_out);
+    if (any(position >= dimensions)) { return; }
+
+    let source = (vec2<f32>(position) + 0.5) / vec2<f32>(dimensions)
+        * vec2<f32>(textureDimensions(canvas_in)) - 0.5;
+    let lower = vec2<i32>(floor(source));
+    let t = fract(source);
+
+    let bottom = mix(load_clamped(lower), load_clamped(lower + vec2<i32>(1, 0)), t.x);
+    let top = mix(load_clamped(lower + vec2<i32>(0, 1)), load_clamped(lower + vec2<i32>(1, 1)), t.x);
+    textureStore(canvas_out, position, mix(bottom, top, t.y));
+}
+
+// agents keep their place relative to the canvas, so they stay on the resampled trail
+@compute @workgroup_size(64)
+fn rescale_agents(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    let idx = global_invocation_id.x;
+    if (idx >= arrayLength(&agents_out)) { return; }
+
+    var agent = agents_in[idx];
+    agent.position *= logical_extent(textureDimensions(canvas_out))
+        / logical_extent(textureDimensions(canvas_in));
+    agents_out[idx] = agent;
+}
@@ -0,0 +1,29 @@
+use crate::{config::CanvasFormat, context::Context};
+
+mod config {
+    pub(super) const CANVAS_FORMAT_PLACEHOLDER: &str = "CANVAS_FORMAT";
+    pub(super) const BLUR_FORMAT_PLACEHOLDER: &str = "BLUR_FORMAT";
+}
+
+// WGSL can't parameterise storage texture formats, so the canvas format and the format of
+// intermediate blur results are substituted into the source before compiling
+pub(crate) fn create_canvas_shader_module(
+    ctx: &Context,
+    name: &str,
+    source: &str,
+    canvas_format: CanvasFormat,
+) -> wgpu::ShaderModule {
+    ctx.device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(&format!("{} Shader", name)),
+            source: wgpu::ShaderSource::Wgsl(
+                source
+                    .replace(config::CANVAS_FORMAT_PLACEHOLDER, canvas_format.wgsl_name())
+                    .replace(
+                        config::BLUR_FORMAT_PLACEHOLDER,
+                        canvas_format.blur_format().wgsl_name(),
+                    )
+                    .into(),
+            ),
+        })
+}
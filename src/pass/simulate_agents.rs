@@ -1,38 +1,77 @@
-use crate::{config::Config, context::Context, state};
+use crate::{
+    agent::MAX_SPECIES,
+    config::{Config, SpeciesConfig},
+    context::Context,
+    interaction::{Attractor, MAX_ATTRACTORS},
+    state,
+};
 
-use super::params::Params;
+use super::{params::Params, shader};
 
 mod config {
     pub(super) const PASS_NAME: &str = "Simulate Agents";
-    pub(super) const SHADER_SOURCE: wgpu::ShaderModuleDescriptor =
-        wgpu::include_wgsl!("simulate_agents.wgsl");
+    pub(super) const SHADER_SOURCE: &str = include_str!("simulate_agents.wgsl");
 
-    pub(super) const SHADER_WORKGROUP_SIZE: u32 = 64;
+    pub(super) const SHADER_WORKGROUP_SIZE: u32 = crate::agent::AGENT_WORKGROUP_SIZE;
 }
 
 #[repr(C)]
 #[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
-struct ParamsData {
+struct SpeciesParamsData {
+    channel_weights: [f32; MAX_SPECIES],
     speed: f32,
     turning_speed: f32,
     sensor_distance: f32,
     sensor_angle: f32,
     sensor_radius: u32,
+    deposit_amount: f32,
+    _pad0: [u32; 2],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct ParamsData {
+    species: [SpeciesParamsData; MAX_SPECIES],
     frame_number: u32,
+    boundary: u32,
+    exclusive_occupancy: u32,
+    deposit_scale: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct AttractorData {
+    position: [f32; 2],
+    radius: f32,
+    strength: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct AttractorsData {
+    attractors: [AttractorData; MAX_ATTRACTORS],
+    num_attractors: u32,
+    _pad0: [u32; 3],
 }
 
 pub(crate) struct SimulateAgents {
     pipeline: wgpu::ComputePipeline,
+    occupancy_pipeline: wgpu::ComputePipeline,
     common_bind_group: wgpu::BindGroup,
     canvas_bind_group: [wgpu::BindGroup; 2],
 
-    #[allow(unused)]
     params: Params<ParamsData>,
+    attractors: Params<AttractorsData>,
 }
 
 impl SimulateAgents {
     pub fn new(ctx: &Context, config: &Config, state: &state::State) -> Self {
         let params = Params::new(ctx, config::PASS_NAME, ParamsData::from(config));
+        let attractors = Params::new(
+            ctx,
+            &format!("{} Attractors", config::PASS_NAME),
+            AttractorsData::default(),
+        );
 
         let common_bind_group_layout =
             ctx.device
@@ -55,6 +94,32 @@ impl SimulateAgents {
                             },
                             count: None,
                         },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: attractors.binding_type(),
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 3,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 4,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
                     ],
                 });
 
@@ -76,10 +141,10 @@ impl SimulateAgents {
                         wgpu::BindGroupLayoutEntry {
                             binding: 1,
                             visibility: wgpu::ShaderStages::COMPUTE,
-                            ty: wgpu::BindingType::StorageTexture {
-                                access: wgpu::StorageTextureAccess::WriteOnly,
-                                format: state::config::CANVAS_FORMAT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: false },
                                 view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
                             },
                             count: None,
                         },
@@ -106,6 +171,18 @@ impl SimulateAgents {
                     binding: 1,
                     resource: state.agents.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: attractors.buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: state.occupancy.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: state.deposits.as_entire_binding(),
+                },
             ],
         });
 
@@ -120,15 +197,18 @@ impl SimulateAgents {
                     },
                     wgpu::BindGroupEntry {
                         binding: 1,
-                        resource: wgpu::BindingResource::TextureView(
-                            &state.canvas_view[(i + 1) % 2],
-                        ),
+                        resource: wgpu::BindingResource::TextureView(&state.mask_view),
                     },
                 ],
             })
         });
 
-        let shader = ctx.device.create_shader_module(config::SHADER_SOURCE);
+        let shader = shader::create_canvas_shader_module(
+            ctx,
+            config::PASS_NAME,
+            config::SHADER_SOURCE,
+            state.canvas_format,
+        );
 
         let pipeline = ctx
             .device
@@ -141,15 +221,42 @@ impl SimulateAgents {
                 cache: None,
             });
 
+        let occupancy_pipeline =
+            ctx.device
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some(&format!("{} Occupancy Pipeline", config::PASS_NAME)),
+                    layout: Some(&pipeline_layout),
+                    module: &shader,
+                    entry_point: Some("mark_occupancy"),
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    cache: None,
+                });
+
         Self {
             pipeline,
+            occupancy_pipeline,
             common_bind_group,
             canvas_bind_group,
 
             params,
+            attractors,
         }
     }
 
+    pub fn update_config(&mut self, ctx: &Context, config: &Config) {
+        self.params.update(ctx, |p| {
+            *p = ParamsData {
+                frame_number: p.frame_number,
+                ..ParamsData::from(config)
+            };
+        });
+    }
+
+    pub fn set_attractors(&mut self, ctx: &Context, attractors: &[Attractor]) {
+        self.attractors
+            .update(ctx, |data| *data = attractors.into());
+    }
+
     pub fn run(
         &mut self,
         ctx: &Context,
@@ -160,26 +267,82 @@ impl SimulateAgents {
             p.frame_number = (state.frame_number % u32::MAX as usize) as _;
         });
 
-        compute_pass.set_pipeline(&self.pipeline);
         compute_pass.set_bind_group(0, &self.common_bind_group, &[]);
         compute_pass.set_bind_group(1, &self.canvas_bind_group[state.frame_number % 2], &[]);
-        compute_pass.dispatch_workgroups(
-            state.num_agents.div_ceil(config::SHADER_WORKGROUP_SIZE),
-            1,
-            1,
-        );
+
+        // every agent's current texel has to be marked before any of them try to move
+        let pipelines = if state.exclusive_occupancy {
+            &[&self.occupancy_pipeline, &self.pipeline][..]
+        } else {
+            &[&self.pipeline][..]
+        };
+
+        for pipeline in pipelines {
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.dispatch_workgroups(
+                state.num_agents.div_ceil(config::SHADER_WORKGROUP_SIZE),
+                1,
+                1,
+            );
+        }
     }
 }
 
 impl From<&Config> for ParamsData {
     fn from(config: &Config) -> Self {
+        let mut species = [SpeciesParamsData::default(); MAX_SPECIES];
+        species
+            .iter_mut()
+            .zip(&config.agent.species)
+            .enumerate()
+            .for_each(|(i, (data, species_config))| {
+                *data = SpeciesParamsData {
+                    channel_weights: species_config.channel_weights(i),
+                    ..species_config.into()
+                };
+            });
+
         Self {
-            speed: config.agent.speed,
-            turning_speed: config.agent.turning_speed,
-            sensor_distance: config.agent.sensor_distance,
-            sensor_angle: config.agent.sensor_angle,
-            sensor_radius: config.agent.sensor_radius,
-            frame_number: 0,
+            species,
+            boundary: config.world.boundary as _,
+            exclusive_occupancy: config.world.exclusive_occupancy.into(),
+            deposit_scale: config.deposit_scale(),
+            ..Default::default()
         }
     }
 }
+
+impl From<&SpeciesConfig> for SpeciesParamsData {
+    fn from(config: &SpeciesConfig) -> Self {
+        Self {
+            speed: config.speed,
+            turning_speed: config.turning_speed,
+            sensor_distance: config.sensor_distance,
+            sensor_angle: config.sensor_angle,
+            sensor_radius: config.sensor_radius,
+            deposit_amount: config.deposit_amount,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&[Attractor]> for AttractorsData {
+    fn from(attractors: &[Attractor]) -> Self {
+        let mut data = Self {
+            num_attractors: attractors.len().min(MAX_ATTRACTORS) as _,
+            ..Default::default()
+        };
+        data.attractors
+            .iter_mut()
+            .zip(attractors)
+            .for_each(|(data, attractor)| {
+                *data = AttractorData {
+                    position: attractor.position,
+                    radius: attractor.radius,
+                    strength: attractor.strength,
+                };
+            });
+
+        data
+    }
+}
@@ -1,34 +1,104 @@
 const PI = radians(180.0);
 const DELTA_TIME: f32 = 1.0 / 60.0;
+const MAX_SPECIES: u32 = 4;
+const MAX_ATTRACTORS: u32 = 16;
 
-struct Params {
+const BOUNDARY_REFLECT: u32 = 0;
+const BOUNDARY_WRAP: u32 = 1;
+const BOUNDARY_RESPAWN: u32 = 2;
+const BOUNDARY_ABSORB: u32 = 3;
+
+// how strongly each wall texel in a sensor's area repels
+const WALL_WEIGHT: f32 = -100.0;
+
+// species of agents that have left the world under the absorb boundary
+const ABSORBED: u32 = 0xffffffffu;
+
+// respawn positions drawn before giving up on one outside the walls
+const MAX_RESPAWN_ATTEMPTS: u32 = 8;
+
+struct SpeciesParams {
+    channel_weights: vec4<f32>,
     speed: f32,
     turning_speed: f32,
     sensor_distance: f32,
     sensor_angle: f32,
     sensor_radius: u32,
+    deposit_amount: f32,
+    _pad0: u32,
+    _pad1: u32,
+};
+
+struct Params {
+    species: array<SpeciesParams, MAX_SPECIES>,
     frame_number: u32,
+    boundary: u32,
+    exclusive_occupancy: u32,
+    // deposits are accumulated in fixed point, see `Config::deposit_scale`
+    deposit_scale: f32,
+};
+
+struct Attractor {
+    position: vec2<f32>,
+    radius: f32,
+    strength: f32,
+};
+
+struct Attractors {
+    attractors: array<Attractor, MAX_ATTRACTORS>,
+    num_attractors: u32,
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
 };
 
 struct Agent {
     position: vec2<f32>,
     heading: f32,
-    _pad0: u32,
+    species: u32,
 };
 
 @group(0) @binding(0) var<uniform> params: Params;
 @group(0) @binding(1) var<storage, read_write> agents : array<Agent>;
+@group(0) @binding(2) var<uniform> attractors: Attractors;
+// agents per texel, cleared every step; only used with exclusive occupancy
+@group(0) @binding(3) var<storage, read_write> occupancy: array<atomic<u32>>;
+// trail deposited this step per texel and species, cleared every step and merged by the world pass
+@group(0) @binding(4) var<storage, read_write> deposits: array<atomic<u32>>;
 
 @group(1) @binding(0) var canvas_in: texture_2d<f32>;
-@group(1) @binding(1) var canvas_out: texture_storage_2d<rgba8unorm, write>;
+@group(1) @binding(1) var mask: texture_2d<f32>;
+
+// half the canvas size in the simulation's space, which spans [-1, 1] vertically and is wider
+// or narrower horizontally so that a unit covers as many texels along both axes
+fn logical_extent() -> vec2<f32> {
+    let dimensions = vec2<f32>(textureDimensions(canvas_in));
+    return vec2<f32>(dimensions.x / dimensions.y, 1.0);
+}
 
 fn clamp_screenspace(pos: vec2<f32>) -> vec2<f32> {
-    return clamp(pos, vec2<f32>(-1), vec2<f32>(1));
+    return clamp(pos, -logical_extent(), logical_extent());
+}
+
+fn wrap_screenspace(pos: vec2<f32>) -> vec2<f32> {
+    let extent = logical_extent();
+    return (fract(pos / extent * 0.5 + 0.5) * 2.0 - 1.0) * extent;
+}
+
+fn outside_screenspace(pos: vec2<f32>) -> bool {
+    return any(abs(pos) > logical_extent());
 }
 
-// maps [-1, 1] to [0, width/height]
+// maps the canvas' logical extent to [0, width/height]
 fn logical_to_physical(pos: vec2<f32>) -> vec2<i32> {
-    return vec2<i32>((pos * 0.5 + 0.5) * vec2<f32>(textureDimensions(canvas_in)));
+    return vec2<i32>((pos / logical_extent() * 0.5 + 0.5) * vec2<f32>(textureDimensions(canvas_in)));
+}
+
+// clamped so that positions on the far edges of the canvas stay in range
+fn texel_index(pos: vec2<i32>) -> u32 {
+    let dimensions = vec2<i32>(textureDimensions(canvas_in));
+    let clamped = clamp(pos, vec2<i32>(0), dimensions - 1);
+    return u32(clamped.y * dimensions.x + clamped.x);
 }
 
 fn rng_next(n: u32) -> u32 {
@@ -46,20 +116,57 @@ fn uint_to_float(n: u32) -> f32 {
     return f32(n) / 4294967295.0;
 }
 
-fn sense(agent: Agent, angle_offset: f32) -> f32 {
+// texels outside the canvas wrap around on a torus and are empty otherwise
+fn load_trail(pos: vec2<i32>) -> vec4<f32> {
+    let dimensions = vec2<i32>(textureDimensions(canvas_in));
+    if (params.boundary == BOUNDARY_WRAP) {
+        return textureLoad(canvas_in, (pos % dimensions + dimensions) % dimensions, 0);
+    }
+    if (any(pos < vec2<i32>(0)) || any(pos >= dimensions)) {
+        return vec4<f32>(0);
+    }
+    return textureLoad(canvas_in, pos, 0);
+}
+
+// follows the same edge policy as `load_trail`, texels outside the canvas are never walls
+fn is_wall(pos: vec2<i32>) -> bool {
+    let dimensions = vec2<i32>(textureDimensions(mask));
+    if (params.boundary == BOUNDARY_WRAP) {
+        return textureLoad(mask, (pos % dimensions + dimensions) % dimensions, 0).r > 0.5;
+    }
+    if (any(pos < vec2<i32>(0)) || any(pos >= dimensions)) {
+        return false;
+    }
+    return textureLoad(mask, pos, 0).r > 0.5;
+}
+
+fn sense(agent: Agent, species: SpeciesParams, angle_offset: f32) -> f32 {
     let angle = agent.heading + angle_offset;
     let delta_pos = vec2<f32>(cos(angle), sin(angle));
-    let sense_location = logical_to_physical(agent.position + delta_pos * params.sensor_distance);
-    let r = i32(params.sensor_radius);
+    let sense_position = agent.position + delta_pos * species.sensor_distance;
+    let sense_location = logical_to_physical(sense_position);
+    let r = i32(species.sensor_radius);
 
     var sum: f32 = 0;
     for (var x: i32 = sense_location.x - r; x <= sense_location.x + r; x += 1) {
         for (var y: i32 = sense_location.y - r; y <= sense_location.y + r; y += 1) {
-            // sum += dot(textureLoad(canvas_in, vec2<i32>(x, y), 0).xy, vec2<f32>(1));
-            // textureStore(canvas_out, vec2<i32>(x, y), vec4<f32>(0, 0, 1, 1));
-            sum += dot(textureLoad(canvas_in, vec2<i32>(x, y), 0).xyz, vec3<f32>(1));
+            let pos = vec2<i32>(x, y);
+            if (is_wall(pos)) {
+                sum += WALL_WEIGHT;
+            } else {
+                sum += dot(load_trail(pos), species.channel_weights);
+            }
         }
     }
+
+    // at full strength an attractor's centre weighs as much as a sensor area full of trail
+    let sensor_area = f32((2 * r + 1) * (2 * r + 1));
+    for (var i: u32 = 0; i < attractors.num_attractors; i += 1u) {
+        let attractor = attractors.attractors[i];
+        let falloff = max(0.0, 1.0 - distance(sense_position, attractor.position) / attractor.radius);
+        sum += attractor.strength * falloff * sensor_area;
+    }
+
     return sum;
 }
 
@@ -71,34 +178,112 @@ fn main(
     if (idx >= arrayLength(&agents)) { return; }
 
     var agent = agents[idx];
+    if (agent.species == ABSORBED) { return; }
+
+    let species = params.species[agent.species];
 
     let physical_pos = logical_to_physical(agent.position);
     var seed = u32(physical_pos.y * 100000 + physical_pos.x) + params.frame_number + u32(idx);
     seed = rng_next(seed);
     let steer_scale = uint_to_float(seed);
 
-    let w_left = sense(agent, -params.sensor_angle);
-    let w_middle = sense(agent, 0.);
-    let w_right = sense(agent, params.sensor_angle);
+    let w_left = sense(agent, species, -species.sensor_angle);
+    let w_middle = sense(agent, species, 0.);
+    let w_right = sense(agent, species, species.sensor_angle);
 
     if (w_left > w_right && w_left > w_middle) {
-        agent.heading -= DELTA_TIME * steer_scale * params.speed * params.turning_speed;
+        agent.heading -= DELTA_TIME * steer_scale * species.speed * species.turning_speed;
     } else if (w_right > w_left && w_right > w_middle) {
-        agent.heading += DELTA_TIME * steer_scale * params.speed * params.turning_speed;
+        agent.heading += DELTA_TIME * steer_scale * species.speed * species.turning_speed;
     }
 
     let delta_position = vec2<f32>(cos(agent.heading), sin(agent.heading));
-    agent.position = clamp_screenspace(agent.position + DELTA_TIME * params.speed * delta_position);
+    let next_position = agent.position + DELTA_TIME * species.speed * delta_position;
 
-    if (agent.position.x <= -1 || agent.position.x >= 1) {
-        agent.heading = PI - agent.heading;
+    switch params.boundary {
+        case BOUNDARY_WRAP: {
+            agent.position = wrap_screenspace(next_position);
+        }
+        case BOUNDARY_RESPAWN: {
+            agent.position = next_position;
+            if (outside_screenspace(next_position)) {
+                // positions in walls are redrawn a few times, if all of them are the agent
+                // stays put like any agent moving into a wall
+                for (var attempt: u32 = 0; attempt < MAX_RESPAWN_ATTEMPTS; attempt += 1u) {
+                    seed = rng_next(seed);
+                    agent.position.x = (uint_to_float(seed) * 2.0 - 1.0) * logical_extent().x;
+                    seed = rng_next(seed);
+                    agent.position.y = uint_to_float(seed) * 2.0 - 1.0;
+                    if (!is_wall(logical_to_physical(agent.position))) { break; }
+                }
+                seed = rng_next(seed);
+                agent.heading = (uint_to_float(seed) * 2.0 - 1.0) * PI;
+            }
+        }
+        case BOUNDARY_ABSORB: {
+            if (outside_screenspace(next_position)) {
+                agent.species = ABSORBED;
+                agents[idx] = agent;
+                return;
+            }
+            agent.position = next_position;
+        }
+        default: {
+            agent.position = clamp_screenspace(next_position);
+
+            let extent = logical_extent();
+            if (agent.position.x <= -extent.x || agent.position.x >= extent.x) {
+                agent.heading = PI - agent.heading;
+            }
+
+            if (agent.position.y <= -extent.y || agent.position.y >= extent.y) {
+                agent.heading = -agent.heading;
+            }
+        }
     }
 
-    if (agent.position.y <= -1 || agent.position.y >= 1) {
-        agent.heading = -agent.heading;
+    // agents can't enter walls, they turn to a random heading instead;
+    // ones that started inside a wall are free to walk out of it
+    if (is_wall(logical_to_physical(agent.position)) && !is_wall(physical_pos)) {
+        agent.position = agents[idx].position;
+        seed = rng_next(seed);
+        agent.heading = uint_to_float(seed) * 2.0 * PI;
+    }
+
+    // only one agent may move into an empty texel, the rest turn and skip depositing;
+    // texels vacated this step only free up on the next one
+    let next_physical_pos = logical_to_physical(agent.position);
+    if (params.exclusive_occupancy != 0 && any(next_physical_pos != physical_pos)) {
+        // an add can't fail spuriously the way a weak compare-exchange can, and the count past
+        // the first claim doesn't matter
+        let claimed = atomicAdd(&occupancy[texel_index(next_physical_pos)], 1u) == 0u;
+        if (!claimed) {
+            agent.position = agents[idx].position;
+            seed = rng_next(seed);
+            agent.heading = uint_to_float(seed) * 2.0 * PI;
+            agents[idx] = agent;
+            return;
+        }
     }
 
     agents[idx] = agent;
-    // textureStore(canvas_out, logical_to_physical(agent.position), vec4<f32>(1, 1, 0, 1));
-    textureStore(canvas_out, logical_to_physical(agent.position), vec4<f32>(1));
+
+    // additions commute, so the result doesn't depend on the order agents run in
+    let dimensions = textureDimensions(canvas_in);
+    let num_species = arrayLength(&deposits) / (dimensions.x * dimensions.y);
+    let deposit_index = texel_index(next_physical_pos) * num_species + agent.species;
+    atomicAdd(&deposits[deposit_index], u32(species.deposit_amount * params.deposit_scale));
+}
+
+@compute @workgroup_size(64)
+fn mark_occupancy(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    let idx = global_invocation_id.x;
+    if (idx >= arrayLength(&agents)) { return; }
+
+    let agent = agents[idx];
+    if (agent.species == ABSORBED) { return; }
+
+    atomicAdd(&occupancy[texel_index(logical_to_physical(agent.position))], 1u);
 }
@@ -1,13 +1,19 @@
-use crate::{config::Config, context::Context, state};
+use crate::{
+    config::{Config, DiffuseKernel, MAX_DIFFUSE_RADIUS},
+    context::Context,
+    food, state,
+};
 
-use super::params::Params;
+use super::{params::Params, shader};
 
 mod config {
     pub(super) const PASS_NAME: &str = "Simulate World";
-    pub(super) const SHADER_SOURCE: wgpu::ShaderModuleDescriptor =
-        wgpu::include_wgsl!("simulate_world.wgsl");
+    pub(super) const SHADER_SOURCE: &str = include_str!("simulate_world.wgsl");
 
-    pub(super) const SHADER_WORKGROUP_SIZE: (u32, u32) = (16, 16);
+    // texels per workgroup along a row in the horizontal blur and a column in the vertical one
+    pub(super) const SHADER_TILE_SIZE: u32 = 256;
+
+    pub(super) const BLUR_WEIGHTS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Float;
 }
 
 #[repr(C)]
@@ -15,14 +21,25 @@ mod config {
 struct ParamsData {
     decay_rate: f32,
     diffuse_radius: u32,
+    boundary: u32,
+    food_scale: f32,
+    // 0: box, 1: gaussian
+    kernel: u32,
+    sigma: f32,
+    deposit_scale: f32,
+    _pad0: u32,
 }
 
+// diffusion is split into a horizontal blur into intermediate textures, and a vertical blur
+// that also applies food and decay on its way into the canvas
 pub(crate) struct SimulateWorld {
+    rows_pipeline: wgpu::ComputePipeline,
     pipeline: wgpu::ComputePipeline,
     common_bind_group: wgpu::BindGroup,
     canvas_bind_group: [wgpu::BindGroup; 2],
+    blur_out_bind_group: wgpu::BindGroup,
+    blur_in_bind_group: wgpu::BindGroup,
 
-    #[allow(unused)]
     params: Params<ParamsData>,
 }
 
@@ -34,12 +51,24 @@ impl SimulateWorld {
             ctx.device
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                     label: Some(&format!("{} Common Bind Group Layout", config::PASS_NAME)),
-                    entries: &[wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: params.binding_type(),
-                        count: None,
-                    }],
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: params.binding_type(),
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
                 });
 
         let canvas_bind_group_layout =
@@ -62,29 +91,146 @@ impl SimulateWorld {
                             visibility: wgpu::ShaderStages::COMPUTE,
                             ty: wgpu::BindingType::StorageTexture {
                                 access: wgpu::StorageTextureAccess::WriteOnly,
-                                format: state::config::CANVAS_FORMAT,
+                                format: state.canvas_format.texture_format(),
                                 view_dimension: wgpu::TextureViewDimension::D2,
                             },
                             count: None,
                         },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 3,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
                     ],
                 });
 
+        let blur_format = state.canvas_format.blur_format().texture_format();
+        let [blur_values_view, blur_weights_view] = [
+            ("Values", blur_format),
+            ("Weights", config::BLUR_WEIGHTS_FORMAT),
+        ]
+        .map(|(name, format)| {
+            ctx.device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(&format!("{} Blur {} Texture", config::PASS_NAME, name)),
+                    size: wgpu::Extent3d {
+                        width: state.dimensions.0,
+                        height: state.dimensions.1,
+                        ..Default::default()
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::STORAGE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+
+        let blur_out_bind_group_layout =
+            ctx.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some(&format!(
+                        "{} Blur Output Bind Group Layout",
+                        config::PASS_NAME
+                    )),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::StorageTexture {
+                                access: wgpu::StorageTextureAccess::WriteOnly,
+                                format: blur_format,
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::StorageTexture {
+                                access: wgpu::StorageTextureAccess::WriteOnly,
+                                format: config::BLUR_WEIGHTS_FORMAT,
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+
+        let blur_in_bind_group_layout =
+            ctx.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some(&format!(
+                        "{} Blur Input Bind Group Layout",
+                        config::PASS_NAME
+                    )),
+                    entries: &[2, 3].map(|binding| wgpu::BindGroupLayoutEntry {
+                        binding,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    }),
+                });
+
+        let rows_pipeline_layout =
+            ctx.device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some(&format!("{} Rows Pipeline Layout", config::PASS_NAME)),
+                    bind_group_layouts: &[
+                        &common_bind_group_layout,
+                        &canvas_bind_group_layout,
+                        &blur_out_bind_group_layout,
+                    ],
+                    ..Default::default()
+                });
+
         let pipeline_layout = ctx
             .device
             .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some(&format!("{} Pipeline Layout", config::PASS_NAME)),
-                bind_group_layouts: &[&common_bind_group_layout, &canvas_bind_group_layout],
+                bind_group_layouts: &[
+                    &common_bind_group_layout,
+                    &canvas_bind_group_layout,
+                    &blur_in_bind_group_layout,
+                ],
                 ..Default::default()
             });
 
         let common_bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some(&format!("{} Common Bind Group", config::PASS_NAME)),
             layout: &common_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: params.buffer.as_entire_binding(),
-            }],
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: state.deposits.as_entire_binding(),
+                },
+            ],
         });
 
         let canvas_bind_group = core::array::from_fn(|i| {
@@ -102,11 +248,65 @@ impl SimulateWorld {
                             &state.canvas_view[(i + 1) % 2],
                         ),
                     },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&state.mask_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(&state.food_view),
+                    },
                 ],
             })
         });
 
-        let shader = ctx.device.create_shader_module(config::SHADER_SOURCE);
+        let blur_out_bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{} Blur Output Bind Group", config::PASS_NAME)),
+            layout: &blur_out_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&blur_values_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&blur_weights_view),
+                },
+            ],
+        });
+
+        let blur_in_bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{} Blur Input Bind Group", config::PASS_NAME)),
+            layout: &blur_in_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&blur_values_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&blur_weights_view),
+                },
+            ],
+        });
+
+        let shader = shader::create_canvas_shader_module(
+            ctx,
+            config::PASS_NAME,
+            config::SHADER_SOURCE,
+            state.canvas_format,
+        );
+
+        let rows_pipeline = ctx
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(&format!("{} Rows Pipeline", config::PASS_NAME)),
+                layout: Some(&rows_pipeline_layout),
+                module: &shader,
+                entry_point: Some("blur_rows"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            });
 
         let pipeline = ctx
             .device
@@ -120,21 +320,38 @@ impl SimulateWorld {
             });
 
         Self {
+            rows_pipeline,
             pipeline,
             common_bind_group,
             canvas_bind_group,
+            blur_out_bind_group,
+            blur_in_bind_group,
 
             params,
         }
     }
 
+    pub fn update_config(&mut self, ctx: &Context, config: &Config) {
+        self.params.update(ctx, |p| *p = ParamsData::from(config));
+    }
+
     pub fn run(&mut self, compute_pass: &mut wgpu::ComputePass, state: &state::State) {
-        compute_pass.set_pipeline(&self.pipeline);
         compute_pass.set_bind_group(0, &self.common_bind_group, &[]);
         compute_pass.set_bind_group(1, &self.canvas_bind_group[state.frame_number % 2], &[]);
+
+        compute_pass.set_pipeline(&self.rows_pipeline);
+        compute_pass.set_bind_group(2, &self.blur_out_bind_group, &[]);
         compute_pass.dispatch_workgroups(
-            state.dimensions.0.div_ceil(config::SHADER_WORKGROUP_SIZE.0),
-            state.dimensions.1.div_ceil(config::SHADER_WORKGROUP_SIZE.1),
+            state.dimensions.0.div_ceil(config::SHADER_TILE_SIZE),
+            state.dimensions.1,
+            1,
+        );
+
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(2, &self.blur_in_bind_group, &[]);
+        compute_pass.dispatch_workgroups(
+            state.dimensions.0,
+            state.dimensions.1.div_ceil(config::SHADER_TILE_SIZE),
             1,
         );
     }
@@ -142,9 +359,20 @@ impl SimulateWorld {
 
 impl From<&Config> for ParamsData {
     fn from(config: &Config) -> Self {
+        let (kernel, sigma) = match config.world.diffuse_kernel {
+            DiffuseKernel::Box => (0, 0.),
+            DiffuseKernel::Gaussian { sigma } => (1, sigma),
+        };
+
         Self {
             decay_rate: config.world.decay_rate,
-            diffuse_radius: config.world.diffuse_radius,
+            diffuse_radius: config.world.diffuse_radius.min(MAX_DIFFUSE_RADIUS),
+            boundary: config.world.boundary as _,
+            food_scale: food::food_scale(config),
+            kernel,
+            sigma,
+            deposit_scale: config.deposit_scale(),
+            ..Default::default()
         }
     }
 }
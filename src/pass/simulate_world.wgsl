@@ -1,34 +1,188 @@
+const BOUNDARY_WRAP: u32 = 1;
+
+const KERNEL_GAUSSIAN: u32 = 1;
+
+// texels along a row (or column) handled by one workgroup, matching the workgroup sizes below
+const TILE_SIZE: u32 = 256;
+// matches `config::MAX_DIFFUSE_RADIUS`
+const MAX_DIFFUSE_RADIUS: u32 = 32;
+const TILE_SPAN: u32 = TILE_SIZE + 2 * MAX_DIFFUSE_RADIUS;
+
 struct Params {
     decay_rate: f32,
     diffuse_radius: u32,
+    boundary: u32,
+    food_scale: f32,
+    kernel: u32,
+    sigma: f32,
+    // deposits are accumulated in fixed point, see `Config::deposit_scale`
+    deposit_scale: f32,
+    _pad0: u32,
 };
 
 @group(0) @binding(0) var<uniform> params: Params;
+// trail the agents deposited this step, per texel and species
+@group(0) @binding(1) var<storage, read> deposits: array<u32>;
 
 @group(1) @binding(0) var canvas_in : texture_2d<f32>;
-@group(1) @binding(1) var canvas_out : texture_storage_2d<rgba8unorm, write>;
+@group(1) @binding(1) var canvas_out : texture_storage_2d<CANVAS_FORMAT, write>;
+@group(1) @binding(2) var mask : texture_2d<f32>;
+@group(1) @binding(3) var food : texture_2d<f32>;
+
+// the horizontal pass writes the row-blurred trail and how much of each row's kernel fell on open
+// texels, which the vertical pass reads back
+@group(2) @binding(0) var blur_values_out : texture_storage_2d<BLUR_FORMAT, write>;
+@group(2) @binding(1) var blur_weights_out : texture_storage_2d<r32float, write>;
+@group(2) @binding(2) var blur_values_in : texture_2d<f32>;
+@group(2) @binding(3) var blur_weights_in : texture_2d<f32>;
+
+var<workgroup> tile_values: array<vec4<f32>, TILE_SPAN>;
+var<workgroup> tile_weights: array<f32, TILE_SPAN>;
+
+fn wrap_position(pos: vec2<i32>, dimensions: vec2<i32>) -> vec2<i32> {
+    return (pos % dimensions + dimensions) % dimensions;
+}
+
+fn outside(pos: vec2<i32>, dimensions: vec2<i32>) -> bool {
+    return any(pos < vec2<i32>(0)) || any(pos >= dimensions);
+}
 
-@compute @workgroup_size(16, 16)
+fn load_deposits(pos: vec2<i32>, dimensions: vec2<i32>) -> vec4<f32> {
+    let num_species = arrayLength(&deposits) / u32(dimensions.x * dimensions.y);
+    let base_index = u32(pos.y * dimensions.x + pos.x) * num_species;
+
+    var deposit = vec4<f32>(0);
+    for (var i: u32 = 0; i < num_species; i += 1u) {
+        deposit[i] = f32(deposits[base_index + i]) / params.deposit_scale;
+    }
+    return deposit;
+}
+
+fn is_wall(pos: vec2<i32>) -> bool {
+    return textureLoad(mask, pos, 0).r > 0.5;
+}
+
+fn kernel_weight(offset: i32) -> f32 {
+    if (params.kernel == KERNEL_GAUSSIAN) {
+        let x = f32(offset) / params.sigma;
+        return exp(-0.5 * x * x);
+    }
+    return 1.0;
+}
+
+fn kernel_total() -> f32 {
+    let r = i32(params.diffuse_radius);
+    var total = 0.0;
+    for (var offset = -r; offset <= r; offset += 1) {
+        total += kernel_weight(offset);
+    }
+    return total;
+}
+
+// both passes stage the texels they need in workgroup memory, so every texel is fetched once
+// per workgroup rather than once per tap
+@compute @workgroup_size(256, 1)
+fn blur_rows(
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+) {
+    let dimensions = vec2<i32>(textureDimensions(canvas_in));
+    let r = i32(params.diffuse_radius);
+    let row = i32(workgroup_id.y);
+    let tile_start = i32(workgroup_id.x * TILE_SIZE) - r;
+
+    for (var i = local_id.x; i < TILE_SIZE + 2 * u32(r); i += TILE_SIZE) {
+        var pos = vec2<i32>(tile_start + i32(i), row);
+        if (params.boundary == BOUNDARY_WRAP) {
+            pos = wrap_position(pos, dimensions);
+        }
+
+        // walls are given no weight, so they neither hold nor soak up trail;
+        // texels outside the canvas wrap around on a torus and are open but empty otherwise
+        if (outside(pos, dimensions)) {
+            tile_values[i] = vec4<f32>(0);
+            tile_weights[i] = 1.0;
+        } else if (is_wall(pos)) {
+            tile_values[i] = vec4<f32>(0);
+            tile_weights[i] = 0.0;
+        } else {
+            tile_values[i] = textureLoad(canvas_in, pos, 0);
+            tile_weights[i] = 1.0;
+        }
+    }
+    workgroupBarrier();
+
+    let position = vec2<i32>(tile_start + r + i32(local_id.x), row);
+    if (position.x >= dimensions.x || position.y >= dimensions.y) { return; }
+
+    var value = vec4<f32>(0);
+    var weight = 0.0;
+    for (var offset = -r; offset <= r; offset += 1) {
+        let i = u32(i32(local_id.x) + r + offset);
+        let k = kernel_weight(offset);
+        value += k * tile_values[i];
+        weight += k * tile_weights[i];
+    }
+
+    // normalized so that 8-bit canvases don't overflow their intermediate format either
+    let total = kernel_total();
+    textureStore(blur_values_out, position, value / total);
+    textureStore(blur_weights_out, position, vec4<f32>(weight / total));
+}
+
+@compute @workgroup_size(1, 256)
 fn main(
-    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>,
 ) {
-    let position = vec2<i32>(global_invocation_id.xy);
     let dimensions = vec2<i32>(textureDimensions(canvas_in));
-    let br = i32(params.diffuse_radius);
+    let r = i32(params.diffuse_radius);
+    let column = i32(workgroup_id.x);
+    let tile_start = i32(workgroup_id.y * TILE_SIZE) - r;
+
+    // a whole row of the kernel outside the canvas counts as open but empty
+    for (var i = local_id.y; i < TILE_SIZE + 2 * u32(r); i += TILE_SIZE) {
+        var pos = vec2<i32>(column, tile_start + i32(i));
+        if (params.boundary == BOUNDARY_WRAP) {
+            pos = wrap_position(pos, dimensions);
+        }
 
+        if (outside(pos, dimensions)) {
+            tile_values[i] = vec4<f32>(0);
+            tile_weights[i] = 1.0;
+        } else {
+            tile_values[i] = textureLoad(blur_values_in, pos, 0);
+            tile_weights[i] = textureLoad(blur_weights_in, pos, 0).r;
+        }
+    }
+    workgroupBarrier();
+
+    let position = vec2<i32>(column, tile_start + r + i32(local_id.y));
     if (position.x >= dimensions.x || position.y >= dimensions.y) { return; }
 
-    let kernel_width = 2 * br + 1;
-    let kernel_elem_count = kernel_width * kernel_width;
-    let kernel_elem_weight = 1.0 / f32(kernel_elem_count);
+    if (is_wall(position)) {
+        textureStore(canvas_out, position, vec4<f32>(0));
+        return;
+    }
 
     var value = vec4<f32>(0);
-    for (var x: i32 = position.x - br; x <= position.x + br; x += 1) {
-        for (var y: i32 = position.y - br; y <= position.y + br; y += 1) {
-            value += kernel_elem_weight * textureLoad(canvas_in, vec2<i32>(x, y), 0);
-        }
+    var weight = 0.0;
+    for (var offset = -r; offset <= r; offset += 1) {
+        let i = u32(i32(local_id.y) + r + offset);
+        let k = kernel_weight(offset);
+        value += k * tile_values[i];
+        weight += k * tile_weights[i];
     }
+    if (weight > 0.0) {
+        value /= weight;
+    }
+
+    // food sources top the trail up to their strength before it decays
+    value = max(value, textureLoad(food, position, 0) * params.food_scale);
     value = max(vec4<f32>(0), value - params.decay_rate);
+    // this step's deposits land on the diffused and decayed trail, so they only start spreading
+    // and fading from the next step
+    value += load_deposits(position, dimensions);
 
     textureStore(canvas_out, position, value);
 }
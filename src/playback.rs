@@ -0,0 +1,54 @@
+use winit::keyboard::KeyCode;
+
+mod config {
+    pub(super) const MAX_SUBSTEPS: u32 = 64;
+}
+
+// how many simulation steps run per rendered frame
+pub(crate) struct Playback {
+    paused: bool,
+    substeps: u32,
+    pending_steps: u32,
+}
+
+impl Playback {
+    pub fn new() -> Self {
+        Self {
+            paused: false,
+            substeps: 1,
+            pending_steps: 0,
+        }
+    }
+
+    // returns whether the key was a playback control
+    pub fn handle_key(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Space => self.paused = !self.paused,
+            KeyCode::Period | KeyCode::ArrowRight if self.paused => self.pending_steps += 1,
+            KeyCode::Equal | KeyCode::NumpadAdd | KeyCode::ArrowUp => {
+                self.substeps = (self.substeps * 2).min(config::MAX_SUBSTEPS);
+            }
+            KeyCode::Minus | KeyCode::NumpadSubtract | KeyCode::ArrowDown => {
+                self.substeps = (self.substeps / 2).max(1);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn take_steps(&mut self) -> u32 {
+        if self.paused {
+            core::mem::take(&mut self.pending_steps)
+        } else {
+            self.substeps
+        }
+    }
+
+    pub fn status(&self) -> String {
+        if self.paused {
+            "Paused".to_owned()
+        } else {
+            format!("{}x", self.substeps)
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use winit::keyboard::KeyCode;
+
+use crate::config::{Config, ConfigError};
+
+mod config {
+    // started with when there's no config file to load, i.e. on the web
+    #[allow(unused)]
+    pub(super) const DEFAULT_PRESET: &str = "hives";
+}
+
+// embeds each file in `interesting_configs/` under its file stem, listed by `build.rs`
+macro_rules! presets {
+    ($($name:literal),* $(,)?) => {
+        &[$(Preset {
+            name: $name,
+            source: include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/interesting_configs/",
+                $name,
+                ".json"
+            )),
+        }),*]
+    };
+}
+
+pub(crate) const PRESETS: &[Preset] = include!(concat!(env!("OUT_DIR"), "/presets.rs"));
+
+pub(crate) struct Preset {
+    pub(crate) name: &'static str,
+    source: &'static str,
+}
+
+impl Preset {
+    pub(crate) fn config(&self) -> Result<Config, ConfigError> {
+        Config::from_json(self.source)
+    }
+}
+
+#[allow(unused)]
+pub(crate) fn default_preset() -> usize {
+    PRESETS
+        .iter()
+        .position(|preset| preset.name == config::DEFAULT_PRESET)
+        .expect("Default preset should be embedded")
+}
+
+// the preset a key switches to from `current`: number keys pick one of the first nine, page
+// down / up step to the next / previous one
+pub(crate) fn handle_key(key: KeyCode, current: Option<usize>) -> Option<usize> {
+    let num_presets = PRESETS.len();
+    let index = match key {
+        KeyCode::PageDown => current.map_or(0, |i| (i + 1) % num_presets),
+        KeyCode::PageUp => current.map_or(num_presets - 1, |i| (i + num_presets - 1) % num_presets),
+        KeyCode::Digit1 => 0,
+        KeyCode::Digit2 => 1,
+        KeyCode::Digit3 => 2,
+        KeyCode::Digit4 => 3,
+        KeyCode::Digit5 => 4,
+        KeyCode::Digit6 => 5,
+        KeyCode::Digit7 => 6,
+        KeyCode::Digit8 => 7,
+        KeyCode::Digit9 => 8,
+        _ => return None,
+    };
+
+    (index < num_presets).then_some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_load() {
+        for preset in PRESETS {
+            if let Err(e) = preset.config() {
+                panic!("Failed to load preset {}: {}", preset.name, e);
+            }
+        }
+    }
+
+    #[test]
+    fn default_preset_is_embedded() {
+        assert_eq!(PRESETS[default_preset()].name, config::DEFAULT_PRESET);
+    }
+}
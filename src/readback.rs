@@ -0,0 +1,111 @@
+use crate::context::Context;
+
+pub(crate) fn padded_bytes_per_row(texture: &wgpu::Texture) -> u32 {
+    unpadded_bytes_per_row(texture).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+pub(crate) fn unpadded_bytes_per_row(texture: &wgpu::Texture) -> u32 {
+    texture.width() * texture.format().block_copy_size(None).unwrap_or(0)
+}
+
+pub(crate) fn create_texture_buffer(ctx: &Context, texture: &wgpu::Texture) -> wgpu::Buffer {
+    ctx.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Texture Readback Buffer"),
+        size: u64::from(padded_bytes_per_row(texture)) * u64::from(texture.height()),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    })
+}
+
+// `buffer` must have been created by `create_texture_buffer` for the same texture
+pub(crate) fn copy_texture_to_buffer(
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    buffer: &wgpu::Buffer,
+) {
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row(texture)),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+}
+
+// blocks until the buffer can be mapped, the copy into it must already have been submitted
+pub(crate) fn read_buffer(ctx: &Context, buffer: &wgpu::Buffer) -> Vec<u8> {
+    let slice = buffer.slice(..);
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    ctx.device.poll(wgpu::Maintain::Wait);
+    receiver
+        .recv()
+        .expect("Failed to receive buffer mapping")
+        .expect("Failed to map readback buffer");
+
+    let data = slice.get_mapped_range().to_vec();
+    buffer.unmap();
+
+    data
+}
+
+// like `read_buffer`, with the row padding required for texture copies stripped
+pub(crate) fn read_texture_buffer(
+    ctx: &Context,
+    texture: &wgpu::Texture,
+    buffer: &wgpu::Buffer,
+) -> Vec<u8> {
+    let row_len = unpadded_bytes_per_row(texture) as usize;
+
+    read_buffer(ctx, buffer)
+        .chunks_exact(padded_bytes_per_row(texture) as usize)
+        .flat_map(|row| &row[..row_len])
+        .copied()
+        .collect()
+}
+
+pub(crate) fn read_texture(ctx: &Context, texture: &wgpu::Texture) -> Vec<u8> {
+    let buffer = create_texture_buffer(ctx, texture);
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Texture Readback Command Encoder"),
+        });
+    copy_texture_to_buffer(&mut encoder, texture, &buffer);
+    ctx.queue.submit(Some(encoder.finish()));
+
+    read_texture_buffer(ctx, texture, &buffer)
+}
+
+pub(crate) fn read_storage_buffer(ctx: &Context, source: &wgpu::Buffer) -> Vec<u8> {
+    let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Buffer Readback Buffer"),
+        size: source.size(),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Buffer Readback Command Encoder"),
+        });
+    encoder.copy_buffer_to_buffer(source, 0, &buffer, 0, source.size());
+    ctx.queue.submit(Some(encoder.finish()));
+
+    read_buffer(ctx, &buffer)
+}
@@ -0,0 +1,67 @@
+use crate::{config::Config, context::Context, pass, state::State};
+
+pub(crate) struct Renderer {
+    pub(crate) camera: pass::Camera,
+    draw_world_pass: pass::DrawWorld,
+    draw_agents_pass: pass::DrawAgents,
+}
+
+impl Renderer {
+    pub fn new(ctx: &Context, config: &Config, state: &State) -> Self {
+        // without a window the canvas is drawn to a target of its own size
+        let target_dimensions = ctx
+            .surface
+            .as_ref()
+            .map_or(state.dimensions, |surface| surface.dimensions());
+        let camera = pass::Camera::new(ctx, config, target_dimensions, state.dimensions);
+        let draw_world_pass = pass::DrawWorld::new(ctx, config, state, &camera);
+        let draw_agents_pass = pass::DrawAgents::new(ctx, config, &camera);
+
+        Self {
+            camera,
+            draw_world_pass,
+            draw_agents_pass,
+        }
+    }
+
+    // for a new simulation state, keeping the current zoom and pan
+    pub fn rebuild(&mut self, ctx: &Context, config: &Config, state: &State) {
+        self.camera.set_canvas(ctx, config, state.dimensions);
+        self.draw_world_pass = pass::DrawWorld::new(ctx, config, state, &self.camera);
+        self.draw_agents_pass = pass::DrawAgents::new(ctx, config, &self.camera);
+    }
+
+    pub fn update_config(&mut self, ctx: &Context, config: &Config) {
+        self.camera.update_config(ctx, config);
+        self.draw_world_pass.update_config(ctx, config);
+        self.draw_agents_pass.update_config(ctx, config);
+    }
+
+    pub fn resize(&mut self, ctx: &Context, target_dimensions: (u32, u32)) {
+        self.camera.resize(ctx, target_dimensions);
+    }
+
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        state: &State,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+
+        self.draw_world_pass
+            .run(&mut render_pass, state, &self.camera);
+        self.draw_agents_pass.run(&mut render_pass, state);
+    }
+}
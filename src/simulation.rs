@@ -0,0 +1,116 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::snapshot::Snapshot;
+use crate::{
+    config::Config,
+    context::Context,
+    interaction::{Attractor, Brush},
+    pass,
+    state::State,
+};
+
+pub(crate) struct Simulation {
+    pub(crate) state: State,
+    simulate_world_pass: pass::SimulateWorld,
+    simulate_agents_pass: pass::SimulateAgents,
+    apply_brush_pass: pass::ApplyBrush,
+}
+
+impl Simulation {
+    pub fn new(ctx: &Context, config: &Config) -> Self {
+        Self::with_state(ctx, config, State::init(ctx, config))
+    }
+
+    // resumes from the saved state, using the config stored alongside it
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn restore(ctx: &Context, snapshot: &Snapshot) -> Self {
+        Self::with_state(ctx, &snapshot.config, State::restore(ctx, snapshot))
+    }
+
+    fn with_state(ctx: &Context, config: &Config, state: State) -> Self {
+        let simulate_world_pass = pass::SimulateWorld::new(ctx, config, &state);
+        let simulate_agents_pass = pass::SimulateAgents::new(ctx, config, &state);
+        let apply_brush_pass = pass::ApplyBrush::new(ctx, &state);
+
+        Self {
+            state,
+
+            simulate_world_pass,
+            simulate_agents_pass,
+            apply_brush_pass,
+        }
+    }
+
+    // only parameters that don't affect buffer sizes; see `needs_rebuild`
+    pub fn update_config(&mut self, ctx: &Context, config: &Config) {
+        if let Err(e) = self.state.set_mask(ctx, config.world.mask.as_deref()) {
+            log::error!("Failed to load mask, keeping the previous one: {}", e);
+        }
+        if let Err(e) = self.state.set_food(ctx, config) {
+            log::error!("Failed to load food, keeping the previous food: {}", e);
+        }
+        self.simulate_world_pass.update_config(ctx, config);
+        self.simulate_agents_pass.update_config(ctx, config);
+    }
+
+    // moves to the config's dimensions, stretching the trail and agents to fit rather than
+    // starting over; everything else has to match the current state
+    pub fn resize(&mut self, ctx: &Context, config: &Config) {
+        let state = self.state.resized(ctx, config);
+
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Resize Command Encoder"),
+            });
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Resize Compute Pass"),
+                ..Default::default()
+            });
+            pass::Resample::new(ctx, &self.state, &state).run(&mut compute_pass, &state);
+        }
+        ctx.queue.submit(Some(encoder.finish()));
+
+        *self = Self::with_state(ctx, config, state);
+    }
+
+    pub fn set_brush(&mut self, ctx: &Context, brush: Option<Brush>) {
+        self.apply_brush_pass.set_brush(ctx, brush);
+    }
+
+    pub fn set_attractors(&mut self, ctx: &Context, attractors: &[Attractor]) {
+        self.simulate_agents_pass.set_attractors(ctx, attractors);
+    }
+
+    pub fn needs_rebuild(&self, config: &Config) -> bool {
+        self.state.dimensions != (config.width, config.height)
+            || self.state.num_agents != config.num_agents
+            || self.state.num_species as usize != config.agent.species.len()
+            || self.state.canvas_format != config.canvas_format
+            || self.state.exclusive_occupancy != config.world.exclusive_occupancy
+    }
+
+    pub fn step(&mut self, ctx: &Context, encoder: &mut wgpu::CommandEncoder) {
+        self.state.update();
+
+        encoder.clear_buffer(&self.state.deposits, 0, None);
+        if self.state.exclusive_occupancy {
+            encoder.clear_buffer(&self.state.occupancy, 0, None);
+        }
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Compute Pass"),
+            ..Default::default()
+        });
+
+        // agents deposit first, so the world pass can fold their deposits into the trail
+        self.simulate_agents_pass
+            .run(ctx, &mut compute_pass, &self.state);
+        self.simulate_world_pass.run(&mut compute_pass, &self.state);
+    }
+
+    // once per rendered frame, whether or not any steps were taken
+    pub fn apply_brush(&self, encoder: &mut wgpu::CommandEncoder) {
+        self.apply_brush_pass.run(encoder, &self.state);
+    }
+}
@@ -0,0 +1,293 @@
+use std::{
+    io::{Read, Write},
+    path::Path,
+};
+
+use crate::{
+    agent::Agent,
+    config::{Config, ConfigError},
+    context::Context,
+    readback,
+    simulation::Simulation,
+};
+
+mod config {
+    pub(super) const MAGIC: &[u8; 8] = b"AGENTSIM";
+    pub(super) const VERSION: u32 = 1;
+    // far more than any config needs, so a corrupt length can't allocate without bound
+    pub(super) const MAX_HEADER_LEN: usize = 1 << 20;
+}
+
+// the config carries the random seed and canvas format along with everything else
+#[derive(serde::Deserialize, serde::Serialize)]
+struct SnapshotHeader {
+    config: Config,
+    frame_number: usize,
+}
+
+// everything needed to resume a run exactly where it was saved:
+// magic, version, JSON header, the raw agent array and the latest canvas texels
+pub(crate) struct Snapshot {
+    pub(crate) config: Config,
+    pub(crate) frame_number: usize,
+    pub(crate) agents: Vec<Agent>,
+    pub(crate) canvas: Vec<u8>,
+}
+
+impl Snapshot {
+    pub fn capture(ctx: &Context, config: &Config, simulation: &Simulation) -> Self {
+        let state = &simulation.state;
+
+        Self {
+            config: config.clone(),
+            frame_number: state.frame_number,
+            agents: agents_from_bytes(&readback::read_storage_buffer(ctx, &state.agents)),
+            canvas: readback::read_texture(ctx, state.latest_canvas()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SnapshotError> {
+        // image paths are made absolute so the snapshot loads from any directory
+        let mut config = self.config.clone();
+        config.resolve_paths(&std::env::current_dir()?);
+
+        let header = serde_json::to_vec(&SnapshotHeader {
+            config,
+            frame_number: self.frame_number,
+        })
+        .map_err(SnapshotError::Parse)?;
+        let agents: &[u8] = bytemuck::cast_slice(&self.agents);
+
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        file.write_all(config::MAGIC)?;
+        file.write_all(&config::VERSION.to_le_bytes())?;
+        for section in [&header[..], agents, &self.canvas] {
+            file.write_all(&(section.len() as u64).to_le_bytes())?;
+            file.write_all(section)?;
+        }
+        file.flush()?;
+
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SnapshotError> {
+        let mut file = std::io::BufReader::new(std::fs::File::open(path)?);
+
+        let mut magic = [0; 8];
+        file.read_exact(&mut magic)?;
+        if &magic != config::MAGIC {
+            return Err(SnapshotError::Format("not a snapshot file".to_owned()));
+        }
+
+        let mut version = [0; 4];
+        file.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != config::VERSION {
+            return Err(SnapshotError::Format(format!(
+                "unsupported snapshot version {}, expected {}",
+                version,
+                config::VERSION
+            )));
+        }
+
+        // sections are only allocated once their length matches what's expected
+        let mut read_section = |name: &str, max_len: usize, exact: bool| {
+            let mut len = [0; 8];
+            file.read_exact(&mut len)?;
+            let len = u64::from_le_bytes(len);
+            if len > max_len as u64 || (exact && len != max_len as u64) {
+                return Err(SnapshotError::Format(format!(
+                    "expected {}{} bytes of {}, found {}",
+                    if exact { "" } else { "at most " },
+                    max_len,
+                    name,
+                    len
+                )));
+            }
+            let mut section = vec![0; len as usize];
+            file.read_exact(&mut section)?;
+            Ok(section)
+        };
+
+        let header: SnapshotHeader =
+            serde_json::from_slice(&read_section("header", config::MAX_HEADER_LEN, false)?)
+                .map_err(SnapshotError::Parse)?;
+
+        // the snapshot may come from an older build or another machine, so its config gets the
+        // same checks as one loaded from a file; values that would be clamped are rejected too,
+        // since the run wouldn't continue as it was saved
+        let saved = serde_json::to_value(&header.config).map_err(SnapshotError::Parse)?;
+        let mut config = header.config;
+        config.validate().map_err(SnapshotError::Config)?;
+        let validated = serde_json::to_value(&config).map_err(SnapshotError::Parse)?;
+        if let Some(path) = first_difference(&saved, &validated) {
+            return Err(SnapshotError::Format(format!(
+                "config value {} is outside the usable range",
+                path
+            )));
+        }
+
+        let agents_len = config.num_agents as usize * size_of::<Agent>();
+        let agents = read_section("agents", agents_len, true)?;
+
+        let bytes_per_texel = config
+            .canvas_format
+            .texture_format()
+            .block_copy_size(None)
+            .unwrap_or(0) as usize;
+        let canvas_len = config.width as usize * config.height as usize * bytes_per_texel;
+        let canvas = read_section("canvas", canvas_len, true)?;
+
+        Ok(Self {
+            config,
+            frame_number: header.frame_number,
+            agents: agents_from_bytes(&agents),
+            canvas,
+        })
+    }
+}
+
+// the dotted path of the first value that differs, like the ones `Config::validate` reports;
+// empty if `a` and `b` are differing values rather than objects or arrays
+fn first_difference(a: &serde_json::Value, b: &serde_json::Value) -> Option<String> {
+    use serde_json::Value;
+
+    let join = |key: &dyn std::fmt::Display, rest: Option<String>| match rest {
+        Some(rest) if !rest.is_empty() => format!("{}.{}", key, rest),
+        _ => key.to_string(),
+    };
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) if a.len() == b.len() => a
+            .iter()
+            .find(|(key, a)| b.get(*key) != Some(a))
+            .map(|(key, a)| join(key, b.get(key).and_then(|b| first_difference(a, b)))),
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => a
+            .iter()
+            .zip(b)
+            .enumerate()
+            .find(|(_, (a, b))| a != b)
+            .map(|(i, (a, b))| join(&i, first_difference(a, b))),
+        _ => (a != b).then(String::new),
+    }
+}
+
+// the bytes aren't necessarily aligned for `Agent`, so they can't be cast in place
+fn agents_from_bytes(bytes: &[u8]) -> Vec<Agent> {
+    bytes
+        .chunks_exact(size_of::<Agent>())
+        .map(bytemuck::pod_read_unaligned)
+        .collect()
+}
+
+#[derive(Debug)]
+pub(crate) enum SnapshotError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    Config(ConfigError),
+    Format(String),
+}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to access snapshot file: {}", e),
+            Self::Parse(e) => write!(f, "failed to parse snapshot header: {}", e),
+            Self::Config(e) => write!(f, "snapshot config: {}", e),
+            Self::Format(reason) => write!(f, "invalid snapshot: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> Snapshot {
+        let mut config: Config =
+            serde_json::from_str(include_str!("../interesting_configs/hives.json")).unwrap();
+        (config.width, config.height, config.num_agents) = (4, 2, 3);
+
+        Snapshot {
+            frame_number: 17,
+            agents: (0..3)
+                .map(|i| Agent::new([i as f32 * 0.1, -0.2], i as f32, 0))
+                .collect(),
+            // 8 texels of rgba16float
+            canvas: (0..64).collect(),
+            config,
+        }
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("agent_sim_{}_{}.bin", std::process::id(), name))
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp_path("round_trip");
+        let saved = snapshot();
+        saved.save(&path).unwrap();
+        let loaded = Snapshot::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.frame_number, saved.frame_number);
+        assert_eq!(
+            bytemuck::cast_slice::<_, u8>(&loaded.agents),
+            bytemuck::cast_slice::<_, u8>(&saved.agents)
+        );
+        assert_eq!(loaded.canvas, saved.canvas);
+        assert_eq!(
+            serde_json::to_string(&loaded.config).unwrap(),
+            serde_json::to_string(&saved.config).unwrap()
+        );
+    }
+
+    #[test]
+    fn load_validates_config() {
+        let path = temp_path("invalid_config");
+        let mut saved = snapshot();
+        saved.config.world.decay_rate = -1.;
+        saved.save(&path).unwrap();
+        let result = Snapshot::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(SnapshotError::Config(_))));
+    }
+
+    #[test]
+    fn load_rejects_values_that_would_be_clamped() {
+        let path = temp_path("clamped_config");
+        let mut saved = snapshot();
+        saved.config.agent.species[0].color[1] = 2.;
+        saved.save(&path).unwrap();
+        let result = Snapshot::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        match result {
+            Err(SnapshotError::Format(reason)) => {
+                assert!(reason.contains("agent.species.0.color.1"), "{}", reason)
+            }
+            _ => panic!("expected the clamped color to be rejected"),
+        }
+    }
+
+    #[test]
+    fn load_rejects_wrong_section_lengths() {
+        let path = temp_path("short_canvas");
+        let mut saved = snapshot();
+        saved.canvas.pop();
+        saved.save(&path).unwrap();
+        let result = Snapshot::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(SnapshotError::Format(_))));
+    }
+}
@@ -0,0 +1,153 @@
+use core::f32::consts::PI;
+
+use fastrand::Rng;
+
+use crate::{
+    agent::Agent,
+    config::{Config, Spawn},
+    image::GreyscaleImage,
+};
+
+mod config {
+    // positions redrawn for an agent that lands in a wall before it's left there to walk out
+    pub(super) const MAX_SPAWN_ATTEMPTS: usize = 64;
+}
+
+// species are interleaved so that every species gets the same spread
+pub(crate) fn spawn_agents(config: &Config) -> Vec<Agent> {
+    let num_species = config.agent.species.len() as u32;
+    let [extent_x, _] = config.logical_extent();
+    let mut rng = Rng::with_seed(config.random_seed);
+
+    let image = match &config.spawn {
+        Spawn::Image { path } => Some(
+            ImageDensity::load(path)
+                .unwrap_or_else(|e| panic!("Failed to load spawn image: {}", e)),
+        ),
+        _ => None,
+    };
+    // a mask that fails to load is reported when it's uploaded, spawning just ignores it
+    let walls = config.world.mask.as_deref().and_then(|path| {
+        GreyscaleImage::load(path)
+            .ok()
+            .map(|image| image.walls((config.width, config.height)))
+    });
+    let in_wall = |[x, y]: [f32; 2]| {
+        let texel = |logical: f32, size: u32| {
+            let texel = ((logical * 0.5 + 0.5) * size as f32).floor();
+            (0. ..size as f32)
+                .contains(&texel)
+                .then_some(texel as usize)
+        };
+        walls.as_ref().is_some_and(|walls| {
+            texel(x / extent_x, config.width)
+                .zip(texel(y, config.height))
+                .is_some_and(|(x, y)| walls[y * config.width as usize + x])
+        })
+    };
+
+    (0..config.num_agents)
+        .map(|i| {
+            let mut sample = || spawn_one(config, &mut rng, image.as_ref(), extent_x);
+            let mut spawned = sample();
+            for _ in 1..config::MAX_SPAWN_ATTEMPTS {
+                if !in_wall(spawned.0) {
+                    break;
+                }
+                spawned = sample();
+            }
+
+            let (position, heading) = spawned;
+            Agent::new(position, heading, i % num_species)
+        })
+        .collect()
+}
+
+// a position and heading following `config.spawn`
+fn spawn_one(
+    config: &Config,
+    rng: &mut Rng,
+    image: Option<&ImageDensity>,
+    extent_x: f32,
+) -> ([f32; 2], f32) {
+    let mut rand_signed_unit = || rng.f32() * 2. - 1.;
+
+    match &config.spawn {
+        Spawn::Uniform => {
+            let position = [rand_signed_unit() * extent_x, rand_signed_unit()];
+            (position, rand_signed_unit() * PI)
+        }
+        Spawn::Point { position } => (*position, rand_signed_unit() * PI),
+        Spawn::Disc { centre, radius } => {
+            // sqrt keeps the density even towards the rim
+            let distance = radius * rng.f32().sqrt();
+            let angle = rng.f32() * 2. * PI;
+            (offset(*centre, distance, angle), rng.f32() * 2. * PI)
+        }
+        Spawn::RingInward { centre, radius } => {
+            let angle = rng.f32() * 2. * PI;
+            (offset(*centre, *radius, angle), angle + PI)
+        }
+        Spawn::RingOutward { centre, radius } => {
+            let angle = rng.f32() * 2. * PI;
+            (offset(*centre, *radius, angle), angle)
+        }
+        Spawn::Image { .. } => {
+            let image = image.expect("Spawn image should be loaded");
+            let [x, y] = image.sample(rng);
+            ([x * extent_x, y], rng.f32() * 2. * PI)
+        }
+    }
+}
+
+fn offset(centre: [f32; 2], distance: f32, angle: f32) -> [f32; 2] {
+    [
+        centre[0] + distance * angle.cos(),
+        centre[1] + distance * angle.sin(),
+    ]
+}
+
+// cumulative brightness over the image's pixels, sampled by inverting it
+struct ImageDensity {
+    width: u32,
+    height: u32,
+    cumulative: Vec<f32>,
+}
+
+impl ImageDensity {
+    fn load(path: &std::path::Path) -> Result<Self, crate::image::ImageError> {
+        let image = GreyscaleImage::load(path)?;
+
+        let cumulative = image
+            .values
+            .iter()
+            .scan(0., |total, &value| {
+                *total += value;
+                Some(*total)
+            })
+            .collect();
+
+        Ok(Self {
+            width: image.width,
+            height: image.height,
+            cumulative,
+        })
+    }
+
+    fn sample(&self, rng: &mut Rng) -> [f32; 2] {
+        let total = self.cumulative.last().copied().unwrap_or(0.);
+        let target = rng.f32() * total;
+        let index = self
+            .cumulative
+            .partition_point(|&c| c <= target)
+            .min(self.cumulative.len() - 1);
+
+        // jittered within the pixel, with the top row of the image at the top of the canvas
+        let x = (index as u32 % self.width) as f32 + rng.f32();
+        let y = (index as u32 / self.width) as f32 + rng.f32();
+        [
+            x / self.width as f32 * 2. - 1.,
+            1. - y / self.height as f32 * 2.,
+        ]
+    }
+}
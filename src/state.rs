@@ -1,46 +1,149 @@
-use fastrand::Rng;
 use wgpu::util::DeviceExt;
 
-use crate::{agent::Agent, config::Config, context::Context};
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
-pub(crate) mod config {
-    pub(crate) const CANVAS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+use crate::{
+    agent::Agent,
+    config::{CanvasFormat, Config, FoodSource},
+    context::Context,
+    food,
+    image::{GreyscaleImage, ImageError},
+    spawn,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{readback, snapshot::Snapshot};
+
+mod config {
+    pub(super) const MASK_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+
+    pub(super) const FOOD_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
 }
 
 pub(crate) struct State {
     pub(crate) dimensions: (u32, u32),
     pub(crate) num_agents: u32,
-    #[allow(dead_code)]
+    pub(crate) num_species: u32,
     pub(crate) agents: wgpu::Buffer,
+    // number of agents per texel, only sized to the canvas when occupancy is exclusive
+    pub(crate) occupancy: wgpu::Buffer,
+    pub(crate) exclusive_occupancy: bool,
+    // fixed-point trail added by the agents this step, one counter per texel and species
+    pub(crate) deposits: wgpu::Buffer,
 
-    #[allow(dead_code)]
     pub(crate) canvas: [wgpu::Texture; 2],
     pub(crate) canvas_view: [wgpu::TextureView; 2],
     pub(crate) canvas_sampler: wgpu::Sampler,
+    pub(crate) canvas_format: CanvasFormat,
+    // 32-bit float formats can't be linearly filtered without an optional device feature
+    pub(crate) canvas_filterable: bool,
+
+    // walls are 1, everything else 0, at the same resolution as the canvas
+    pub(crate) mask: wgpu::Texture,
+    pub(crate) mask_view: wgpu::TextureView,
+    // the loaded image's path and modification time, if the mask came from one
+    mask_source: Option<(PathBuf, Option<SystemTime>)>,
+
+    // nutrient sources, see `food::rasterize`
+    pub(crate) food: wgpu::Texture,
+    pub(crate) food_view: wgpu::TextureView,
+    food_sources: Option<Vec<FoodSource>>,
 
     pub(crate) frame_number: usize,
 }
 
 impl State {
     pub fn init(ctx: &Context, config: &Config) -> Self {
+        Self::with_agents(ctx, config, &spawn::spawn_agents(config))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn restore(ctx: &Context, snapshot: &Snapshot) -> Self {
+        let mut state = Self::with_agents(ctx, &snapshot.config, &snapshot.agents);
+        state.frame_number = snapshot.frame_number;
+
+        let canvas = state.latest_canvas();
+        ctx.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: canvas,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &snapshot.canvas,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(readback::unpadded_bytes_per_row(canvas)),
+                rows_per_image: None,
+            },
+            canvas.size(),
+        );
+
+        state
+    }
+
+    // an empty state at the config's dimensions with room for this one's agents, for
+    // `pass::Resample` to carry them and the trail over into
+    pub fn resized(&self, ctx: &Context, config: &Config) -> Self {
+        let agents = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Agents Buffer"),
+            size: self.agents.size(),
+            usage: self.agents.usage(),
+            mapped_at_creation: false,
+        });
+
+        let mut state = Self::with_agents_buffer(ctx, config, agents);
+        state.frame_number = self.frame_number;
+
+        state
+    }
+
+    fn with_agents(ctx: &Context, config: &Config, agents: &[Agent]) -> Self {
+        let agents = ctx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Agents Buffer"),
+                contents: bytemuck::cast_slice(agents),
+                usage: wgpu::BufferUsages::VERTEX
+                    | wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::COPY_SRC,
+            });
+
+        Self::with_agents_buffer(ctx, config, agents)
+    }
+
+    fn with_agents_buffer(ctx: &Context, config: &Config, agents: wgpu::Buffer) -> Self {
         let dimensions = (config.width, config.height);
         let num_agents = config.num_agents;
+        let num_species = config.agent.species.len() as u32;
+        let canvas_format = config.canvas_format;
+        let canvas_filterable = canvas_format
+            .texture_format()
+            .guaranteed_format_features(ctx.device.features())
+            .flags
+            .contains(wgpu::TextureFormatFeatureFlags::FILTERABLE);
 
-        let agents = {
-            let mut rng = Rng::with_seed(config.random_seed);
-            let initial_data = (0..num_agents)
-                .map(|_| Agent::new(|| rng.f32()))
-                .collect::<Vec<_>>();
-
-            ctx.device
-                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Agents Buffer"),
-                    contents: bytemuck::cast_slice(&initial_data),
-                    usage: wgpu::BufferUsages::VERTEX
-                        | wgpu::BufferUsages::STORAGE
-                        | wgpu::BufferUsages::COPY_DST,
-                })
-        };
+        let exclusive_occupancy = config.world.exclusive_occupancy;
+        let occupancy = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Occupancy Buffer"),
+            size: if exclusive_occupancy {
+                u64::from(dimensions.0) * u64::from(dimensions.1) * 4
+            } else {
+                4
+            },
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let deposits = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Deposits Buffer"),
+            size: u64::from(dimensions.0) * u64::from(dimensions.1) * u64::from(num_species) * 4,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
 
         let canvas = core::array::from_fn(|i| {
             ctx.device.create_texture(&wgpu::TextureDescriptor {
@@ -53,8 +156,11 @@ impl State {
                 mip_level_count: 1,
                 sample_count: 1,
                 dimension: wgpu::TextureDimension::D2,
-                format: config::CANVAS_FORMAT,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+                format: canvas_format.texture_format(),
+                usage: wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::STORAGE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC
+                    | wgpu::TextureUsages::COPY_DST,
                 view_formats: &[],
             })
         });
@@ -66,29 +172,175 @@ impl State {
             })
         });
 
+        let canvas_filter_mode = if canvas_filterable {
+            wgpu::FilterMode::Linear
+        } else {
+            wgpu::FilterMode::Nearest
+        };
+
         let canvas_sampler = ctx.device.create_sampler(&wgpu::SamplerDescriptor {
             label: Some("Canvas Texture Sampler"),
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Linear,
+            mag_filter: canvas_filter_mode,
+            min_filter: canvas_filter_mode,
+            mipmap_filter: canvas_filter_mode,
+            ..Default::default()
+        });
+
+        let mask = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Mask Texture"),
+            size: wgpu::Extent3d {
+                width: dimensions.0,
+                height: dimensions.1,
+                ..Default::default()
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config::MASK_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let mask_view = mask.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Mask Texture View"),
+            ..Default::default()
+        });
+
+        let food = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Food Texture"),
+            size: wgpu::Extent3d {
+                width: dimensions.0,
+                height: dimensions.1,
+                ..Default::default()
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config::FOOD_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let food_view = food.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Food Texture View"),
             ..Default::default()
         });
 
-        Self {
+        let mut state = Self {
             dimensions,
             num_agents,
+            num_species,
 
             agents,
+            occupancy,
+            exclusive_occupancy,
+            deposits,
 
             canvas,
             canvas_view,
             canvas_sampler,
+            canvas_format,
+            canvas_filterable,
+
+            mask,
+            mask_view,
+            mask_source: None,
+
+            food,
+            food_view,
+            food_sources: None,
 
             frame_number: 0,
+        };
+        if let Err(e) = state.set_mask(ctx, config.world.mask.as_deref()) {
+            log::error!("Failed to load mask, starting without walls: {}", e);
         }
+        if let Err(e) = state.set_food(ctx, config) {
+            log::error!("Failed to load food, starting without any: {}", e);
+        }
+
+        state
+    }
+
+    // only reloaded when the path or the file's modification time changes; the previous mask is
+    // kept if the new one can't be loaded
+    pub fn set_mask(&mut self, ctx: &Context, path: Option<&Path>) -> Result<(), ImageError> {
+        let source = path.map(|path| {
+            let modified = std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            (path.to_path_buf(), modified)
+        });
+        if source.is_some() && self.mask_source == source {
+            return Ok(());
+        }
+
+        let walls = match path {
+            Some(path) => GreyscaleImage::load(path)?
+                .walls(self.dimensions)
+                .into_iter()
+                .map(|wall| if wall { 255 } else { 0 })
+                .collect(),
+            None => vec![0; (self.dimensions.0 * self.dimensions.1) as usize],
+        };
+        self.mask_source = source;
+
+        ctx.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.mask,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &walls,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(self.dimensions.0),
+                rows_per_image: None,
+            },
+            self.mask.size(),
+        );
+
+        Ok(())
+    }
+
+    // only redrawn when the sources change; the previous food is kept if an image can't be loaded
+    pub fn set_food(&mut self, ctx: &Context, config: &Config) -> Result<(), ImageError> {
+        if self.food_sources.as_ref() == Some(&config.world.food) {
+            return Ok(());
+        }
+        let field = food::rasterize(config, self.dimensions)?;
+        self.food_sources = Some(config.world.food.clone());
+
+        ctx.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.food,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &field,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(self.dimensions.0 * 4),
+                rows_per_image: None,
+            },
+            self.food.size(),
+        );
+
+        Ok(())
     }
 
     pub fn update(&mut self) {
         self.frame_number += 1;
     }
+
+    // the canvas written by the most recent step, and read by the next one
+    pub fn latest_canvas(&self) -> &wgpu::Texture {
+        &self.canvas[(self.frame_number + 1) % 2]
+    }
+
+    pub fn latest_canvas_view(&self) -> &wgpu::TextureView {
+        &self.canvas_view[(self.frame_number + 1) % 2]
+    }
 }
@@ -0,0 +1,190 @@
+use std::{fmt::Write as _, path::PathBuf};
+
+use crate::{context::Context, readback, simulation::Simulation};
+
+mod config {
+    // FNV-1a, chosen because its output is stable across platforms and Rust versions
+    pub(super) const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    pub(super) const FNV_PRIME: u64 = 0x100000001b3;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FrameHash {
+    frame_number: usize,
+    agents: u64,
+    canvas: u64,
+}
+
+impl FrameHash {
+    // reads back the whole simulation state, so the step must already have been submitted
+    pub fn capture(ctx: &Context, simulation: &Simulation) -> Self {
+        let state = &simulation.state;
+
+        Self {
+            frame_number: state.frame_number,
+            agents: hash(&readback::read_storage_buffer(ctx, &state.agents)),
+            canvas: hash(&readback::read_texture(ctx, state.latest_canvas())),
+        }
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        let hash = Self {
+            frame_number: fields.next()?.parse().ok()?,
+            agents: u64::from_str_radix(fields.next()?, 16).ok()?,
+            canvas: u64::from_str_radix(fields.next()?, 16).ok()?,
+        };
+        fields.next().is_none().then_some(hash)
+    }
+}
+
+impl std::fmt::Display for FrameHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:016x} {:016x}",
+            self.frame_number, self.agents, self.canvas
+        )
+    }
+}
+
+pub(crate) enum TraceMode {
+    Record(PathBuf),
+    Verify(PathBuf),
+}
+
+// per-frame hashes of the agent buffer and canvas, either written out or checked against a
+// previously recorded run
+pub(crate) struct Trace {
+    mode: TraceMode,
+    expected: Vec<FrameHash>,
+    recorded: Vec<FrameHash>,
+    num_mismatches: usize,
+}
+
+impl Trace {
+    pub fn new(mode: TraceMode) -> Result<Self, TraceError> {
+        let expected = match &mode {
+            TraceMode::Record(_) => Vec::new(),
+            TraceMode::Verify(path) => std::fs::read_to_string(path)
+                .map_err(TraceError::Io)?
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+                .map(|(i, line)| FrameHash::parse(line).ok_or(TraceError::Parse(i + 1)))
+                .collect::<Result<_, _>>()?,
+        };
+
+        Ok(Self {
+            mode,
+            expected,
+            recorded: Vec::new(),
+            num_mismatches: 0,
+        })
+    }
+
+    // number of frames in the trace being verified against
+    pub fn expected_frames(&self) -> Option<usize> {
+        matches!(self.mode, TraceMode::Verify(_)).then_some(self.expected.len())
+    }
+
+    pub fn record(&mut self, hash: FrameHash) {
+        if let TraceMode::Verify(_) = self.mode {
+            match self.expected.get(self.recorded.len()) {
+                Some(expected) if *expected == hash => {}
+                Some(expected) => {
+                    self.num_mismatches += 1;
+                    log::error!("Frame diverged, expected {} found {}", expected, hash);
+                }
+                None => log::warn!("Frame {} is past the end of the trace", hash.frame_number),
+            }
+        }
+
+        self.recorded.push(hash);
+    }
+
+    pub fn finish(self) -> Result<(), TraceError> {
+        match self.mode {
+            TraceMode::Record(path) => {
+                let mut contents = String::from("# frame agents canvas\n");
+                for hash in &self.recorded {
+                    let _ = writeln!(contents, "{}", hash);
+                }
+                std::fs::write(&path, contents).map_err(TraceError::Io)?;
+
+                log::info!(
+                    "Wrote {} frame hashes to {}",
+                    self.recorded.len(),
+                    path.display()
+                );
+                Ok(())
+            }
+            TraceMode::Verify(_) if self.num_mismatches > 0 => Err(TraceError::Diverged {
+                num_mismatches: self.num_mismatches,
+                num_frames: self.recorded.len(),
+            }),
+            TraceMode::Verify(_) => {
+                log::info!("All {} frames match the trace", self.recorded.len());
+                Ok(())
+            }
+        }
+    }
+}
+
+fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(config::FNV_OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(config::FNV_PRIME)
+    })
+}
+
+#[derive(Debug)]
+pub(crate) enum TraceError {
+    Io(std::io::Error),
+    Parse(usize),
+    Diverged {
+        num_mismatches: usize,
+        num_frames: usize,
+    },
+}
+
+impl std::fmt::Display for TraceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to access trace file: {}", e),
+            Self::Parse(line) => write!(f, "malformed frame hash on line {}", line),
+            Self::Diverged {
+                num_mismatches,
+                num_frames,
+            } => write!(
+                f,
+                "{} of {} frames differ from the trace",
+                num_mismatches, num_frames
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_hash_round_trip() {
+        let hash = FrameHash {
+            frame_number: 42,
+            agents: 0xdead_beef_0123_4567,
+            canvas: 1,
+        };
+        assert_eq!(hash.to_string(), "42 deadbeef01234567 0000000000000001");
+        assert!(FrameHash::parse(&hash.to_string()) == Some(hash));
+    }
+
+    #[test]
+    fn frame_hash_rejects_malformed_lines() {
+        for line in ["", "1 2", "1 zz 3", "1 2 3 4", "-1 2 3"] {
+            assert!(FrameHash::parse(line).is_none(), "{:?}", line);
+        }
+    }
+}
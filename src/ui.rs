@@ -0,0 +1,218 @@
+use winit::{
+    event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+    keyboard::{Key, ModifiersState},
+    window::Window,
+};
+
+use crate::context::Context;
+
+// feeds window events to egui and draws what it produces over the rendered frame
+pub(crate) struct Ui {
+    ctx: egui::Context,
+    renderer: egui_wgpu::Renderer,
+
+    // collected between frames, in egui's points rather than pixels
+    events: Vec<egui::Event>,
+    modifiers: egui::Modifiers,
+    pointer: egui::Pos2,
+
+    // output of the last `run`, drawn by the next `render`
+    paint_jobs: Vec<egui::ClippedPrimitive>,
+    textures_delta: egui::TexturesDelta,
+    screen: egui_wgpu::ScreenDescriptor,
+}
+
+impl Ui {
+    pub fn new(ctx: &Context) -> Self {
+        Self {
+            ctx: egui::Context::default(),
+            renderer: egui_wgpu::Renderer::new(&ctx.device, ctx.target_format(), None, 1, false),
+
+            events: Vec::new(),
+            modifiers: egui::Modifiers::default(),
+            pointer: egui::Pos2::ZERO,
+
+            paint_jobs: Vec::new(),
+            textures_delta: egui::TexturesDelta::default(),
+            screen: egui_wgpu::ScreenDescriptor {
+                size_in_pixels: [0; 2],
+                pixels_per_point: 1.,
+            },
+        }
+    }
+
+    // returns whether egui is using the event, so it shouldn't also reach the simulation;
+    // cursor movement always gets through so the simulation knows where the cursor is
+    pub fn handle_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        let scale_factor = window.scale_factor() as f32;
+
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.pointer = egui::pos2(position.x as f32, position.y as f32) / scale_factor;
+                self.events.push(egui::Event::PointerMoved(self.pointer));
+                false
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.events.push(egui::Event::PointerGone);
+                false
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let button = match button {
+                    MouseButton::Left => egui::PointerButton::Primary,
+                    MouseButton::Right => egui::PointerButton::Secondary,
+                    MouseButton::Middle => egui::PointerButton::Middle,
+                    _ => return false,
+                };
+                self.events.push(egui::Event::PointerButton {
+                    pos: self.pointer,
+                    button,
+                    pressed: state.is_pressed(),
+                    modifiers: self.modifiers,
+                });
+                // releases always get through, so strokes started outside egui end
+                state.is_pressed() && self.wants_pointer()
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (unit, delta) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => {
+                        (egui::MouseWheelUnit::Line, egui::vec2(*x, *y))
+                    }
+                    MouseScrollDelta::PixelDelta(pixels) => (
+                        egui::MouseWheelUnit::Point,
+                        egui::vec2(pixels.x as f32, pixels.y as f32) / scale_factor,
+                    ),
+                };
+                self.events.push(egui::Event::MouseWheel {
+                    unit,
+                    delta,
+                    modifiers: self.modifiers,
+                });
+                self.wants_pointer()
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = convert_modifiers(modifiers.state());
+                false
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                let pressed = event.state == ElementState::Pressed;
+                let key = match &event.logical_key {
+                    Key::Named(named) => egui::Key::from_name(&format!("{:?}", named)),
+                    Key::Character(c) => egui::Key::from_name(c),
+                    _ => None,
+                };
+                if let Some(key) = key {
+                    self.events.push(egui::Event::Key {
+                        key,
+                        physical_key: None,
+                        pressed,
+                        repeat: false,
+                        modifiers: self.modifiers,
+                    });
+                }
+                if let Some(text) = event.text.as_ref().filter(|_| pressed)
+                    && !text.chars().any(char::is_control)
+                {
+                    self.events.push(egui::Event::Text(text.to_string()));
+                }
+                self.ctx.wants_keyboard_input()
+            }
+            _ => false,
+        }
+    }
+
+    fn wants_pointer(&self) -> bool {
+        self.ctx.is_pointer_over_area() || self.ctx.is_using_pointer()
+    }
+
+    // builds this frame's interface with `build`, to be drawn by `render`
+    pub fn run(&mut self, ctx: &Context, window: &Window, build: impl FnMut(&egui::Context)) {
+        let scale_factor = window.scale_factor() as f32;
+        let size = window.inner_size();
+        let screen_size = egui::vec2(size.width as f32, size.height as f32) / scale_factor;
+
+        let mut raw_input = egui::RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(egui::Pos2::ZERO, screen_size)),
+            max_texture_side: Some(ctx.device.limits().max_texture_dimension_2d as usize),
+            modifiers: self.modifiers,
+            events: std::mem::take(&mut self.events),
+            ..Default::default()
+        };
+        raw_input
+            .viewports
+            .entry(raw_input.viewport_id)
+            .or_default()
+            .native_pixels_per_point = Some(scale_factor);
+
+        let output = self.ctx.run(raw_input, build);
+
+        self.paint_jobs = self.ctx.tessellate(output.shapes, output.pixels_per_point);
+        self.textures_delta.append(output.textures_delta);
+
+        // the surface isn't always at the window's physical size, see `Surface::set_dimensions`
+        let (width, height) = ctx.surface().dimensions();
+        self.screen = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [width, height],
+            pixels_per_point: width as f32 / screen_size.x.max(1.),
+        };
+    }
+
+    pub fn render(
+        &mut self,
+        ctx: &Context,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+    ) {
+        let textures_delta = std::mem::take(&mut self.textures_delta);
+        for (id, image_delta) in &textures_delta.set {
+            self.renderer
+                .update_texture(&ctx.device, &ctx.queue, *id, image_delta);
+        }
+
+        let command_buffers = self.renderer.update_buffers(
+            &ctx.device,
+            &ctx.queue,
+            encoder,
+            &self.paint_jobs,
+            &self.screen,
+        );
+        ctx.queue.submit(command_buffers);
+
+        {
+            let mut render_pass = encoder
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("UI Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: target,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    ..Default::default()
+                })
+                .forget_lifetime();
+
+            self.renderer
+                .render(&mut render_pass, &self.paint_jobs, &self.screen);
+        }
+
+        for id in &textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
+
+fn convert_modifiers(state: ModifiersState) -> egui::Modifiers {
+    egui::Modifiers {
+        alt: state.alt_key(),
+        ctrl: state.control_key(),
+        shift: state.shift_key(),
+        mac_cmd: cfg!(target_os = "macos") && state.super_key(),
+        command: if cfg!(target_os = "macos") {
+            state.super_key()
+        } else {
+            state.control_key()
+        },
+    }
+}
@@ -0,0 +1,63 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime},
+};
+
+use crate::config::{Config, ConfigError};
+
+mod config {
+    pub(super) const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+}
+
+pub(crate) struct ConfigWatcher {
+    path: PathBuf,
+    load: Box<dyn Fn() -> Result<Config, ConfigError>>,
+
+    last_modified: Option<SystemTime>,
+    last_checked: Instant,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf, load: impl Fn() -> Result<Config, ConfigError> + 'static) -> Self {
+        let last_modified = Self::modified_time(&path);
+
+        Self {
+            path,
+            load: Box::new(load),
+
+            last_modified,
+            last_checked: Instant::now(),
+        }
+    }
+
+    // returns the reloaded config if the file has changed since the last poll
+    pub fn poll(&mut self) -> Option<Config> {
+        if self.last_checked.elapsed() < config::POLL_INTERVAL {
+            return None;
+        }
+        self.last_checked = Instant::now();
+
+        let modified = Self::modified_time(&self.path);
+        if modified == self.last_modified {
+            return None;
+        }
+        self.last_modified = modified;
+
+        match (self.load)() {
+            Ok(config) => {
+                log::info!("Reloaded config from {}", self.path.display());
+                Some(config)
+            }
+            Err(e) => {
+                log::error!("Keeping previous config, {}", e);
+                None
+            }
+        }
+    }
+
+    fn modified_time(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+}